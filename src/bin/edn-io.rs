@@ -0,0 +1,411 @@
+//! `edn-io` - inspect and convert Embeddenator envelope files
+//!
+//! Build with `--features cli` (add `full-compression` for zstd/LZ4 support).
+//!
+//! ```text
+//! edn-io inspect engram.bin
+//! edn-io wrap --kind engram --codec zstd --level 9 raw.bin engram.bin
+//! edn-io unwrap --kind engram engram.bin raw.bin
+//! edn-io compress --codec lz4 data.bin data.bin.lz4
+//! edn-io decompress --codec lz4 data.bin.lz4 data.bin
+//! edn-io profile /usr/lib/libssl.so.3
+//! edn-io --json verify engram.bin
+//! ```
+
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use embeddenator_io::{
+    compress_file, decompress_file, unwrap_auto, wrap_or_legacy, BinaryWriteOptions,
+    CompressionCodec, CompressionLevel, CompressionProfiler, EnvelopeHeader, PayloadKind,
+};
+use serde_json::{json, Value};
+
+#[derive(Parser)]
+#[command(
+    name = "edn-io",
+    version,
+    about = "Inspect and convert Embeddenator envelope files"
+)]
+struct Cli {
+    /// Emit machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show envelope header fields
+    Inspect {
+        /// File to inspect
+        path: PathBuf,
+    },
+    /// Wrap a raw payload in an envelope
+    Wrap {
+        /// Payload kind (engram, sub-engram)
+        #[arg(long, value_parser = parse_kind)]
+        kind: PayloadKind,
+        /// Compression codec (none, zstd, lz4)
+        #[arg(long, value_parser = parse_codec)]
+        codec: CompressionCodec,
+        /// Codec-specific compression level
+        #[arg(long, allow_negative_numbers = true)]
+        level: Option<i32>,
+        /// Raw input file
+        input: PathBuf,
+        /// Envelope output file
+        output: PathBuf,
+    },
+    /// Extract the raw payload from an envelope (legacy files pass through)
+    Unwrap {
+        /// Expected payload kind (engram, sub-engram)
+        #[arg(long, value_parser = parse_kind)]
+        kind: PayloadKind,
+        /// Envelope input file
+        input: PathBuf,
+        /// Raw output file
+        output: PathBuf,
+    },
+    /// Stream-compress a file
+    Compress {
+        /// Compression codec (none, zstd, lz4)
+        #[arg(long, value_parser = parse_codec)]
+        codec: CompressionCodec,
+        /// Compression level (fast, default, best or a number)
+        #[arg(long, default_value = "default", value_parser = parse_level)]
+        level: CompressionLevel,
+        /// Uncompressed input file
+        input: PathBuf,
+        /// Compressed output file
+        output: PathBuf,
+    },
+    /// Stream-decompress a file
+    Decompress {
+        /// Compression codec used for the input (none, zstd, lz4)
+        #[arg(long, value_parser = parse_codec)]
+        codec: CompressionCodec,
+        /// Compressed input file
+        input: PathBuf,
+        /// Uncompressed output file
+        output: PathBuf,
+    },
+    /// Show the compression profile selected for a path
+    Profile {
+        /// Path to classify (does not need to exist)
+        path: String,
+    },
+    /// Check envelope integrity by fully decoding the payload
+    Verify {
+        /// Envelope file to verify
+        path: PathBuf,
+    },
+}
+
+fn parse_kind(s: &str) -> Result<PayloadKind, String> {
+    PayloadKind::from_name(s).ok_or_else(|| format!("unknown payload kind `{s}`"))
+}
+
+fn parse_codec(s: &str) -> Result<CompressionCodec, String> {
+    CompressionCodec::from_name(s).ok_or_else(|| format!("unknown codec `{s}`"))
+}
+
+fn parse_level(s: &str) -> Result<CompressionLevel, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "fast" => Ok(CompressionLevel::Fast),
+        "default" => Ok(CompressionLevel::Default),
+        "best" => Ok(CompressionLevel::Best),
+        other => other
+            .parse()
+            .map(CompressionLevel::Custom)
+            .map_err(|_| format!("invalid compression level `{s}`")),
+    }
+}
+
+/// Result of a subcommand: JSON report plus the text rendering of it
+struct Report {
+    value: Value,
+    text: String,
+    success: bool,
+}
+
+impl Report {
+    fn ok(value: Value, text: String) -> Self {
+        Self {
+            value,
+            text,
+            success: true,
+        }
+    }
+}
+
+fn header_json(header: Option<&EnvelopeHeader>) -> Value {
+    match header {
+        Some(h) => json!({
+            "kind": h.kind.name(),
+            "codec": h.codec.name(),
+            "reserved": h.reserved,
+            "uncompressed_len": h.uncompressed_len,
+        }),
+        None => Value::Null,
+    }
+}
+
+fn inspect(path: PathBuf) -> io::Result<Report> {
+    let data = std::fs::read(&path)?;
+    let header = EnvelopeHeader::parse(&data)?;
+    let file_len = data.len() as u64;
+
+    let value = json!({
+        "path": path.display().to_string(),
+        "file_len": file_len,
+        "format": if header.is_some() { "envelope" } else { "legacy" },
+        "header": header_json(header.as_ref()),
+        "payload_len": header.map(|_| file_len - EnvelopeHeader::LEN as u64),
+    });
+
+    let text = match header {
+        Some(h) => format!(
+            "{}: envelope\n  kind:             {}\n  codec:            {}\n  reserved:         {}\n  uncompressed_len: {}\n  payload_len:      {}\n  file_len:         {}",
+            path.display(),
+            h.kind.name(),
+            h.codec.name(),
+            h.reserved,
+            h.uncompressed_len,
+            file_len - EnvelopeHeader::LEN as u64,
+            file_len,
+        ),
+        None => format!(
+            "{}: legacy (no envelope header)\n  file_len:         {}",
+            path.display(),
+            file_len
+        ),
+    };
+
+    Ok(Report::ok(value, text))
+}
+
+fn wrap(
+    kind: PayloadKind,
+    codec: CompressionCodec,
+    level: Option<i32>,
+    input: PathBuf,
+    output: PathBuf,
+) -> io::Result<Report> {
+    let raw = std::fs::read(&input)?;
+    let wrapped = wrap_or_legacy(kind, BinaryWriteOptions { codec, level }, &raw)?;
+    std::fs::write(&output, &wrapped)?;
+
+    let value = json!({
+        "input": input.display().to_string(),
+        "output": output.display().to_string(),
+        "kind": kind.name(),
+        "codec": codec.name(),
+        "level": level,
+        "input_len": raw.len(),
+        "output_len": wrapped.len(),
+    });
+    let text = format!(
+        "wrapped {} -> {} ({} -> {} bytes, {} {})",
+        input.display(),
+        output.display(),
+        raw.len(),
+        wrapped.len(),
+        kind.name(),
+        codec.name()
+    );
+    Ok(Report::ok(value, text))
+}
+
+fn unwrap(kind: PayloadKind, input: PathBuf, output: PathBuf) -> io::Result<Report> {
+    let data = std::fs::read(&input)?;
+    let header = EnvelopeHeader::parse(&data)?;
+    let raw = unwrap_auto(kind, &data)?;
+    std::fs::write(&output, &raw)?;
+
+    let value = json!({
+        "input": input.display().to_string(),
+        "output": output.display().to_string(),
+        "format": if header.is_some() { "envelope" } else { "legacy" },
+        "input_len": data.len(),
+        "output_len": raw.len(),
+    });
+    let text = format!(
+        "unwrapped {} -> {} ({} -> {} bytes)",
+        input.display(),
+        output.display(),
+        data.len(),
+        raw.len()
+    );
+    Ok(Report::ok(value, text))
+}
+
+fn compress(
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    input: PathBuf,
+    output: PathBuf,
+) -> io::Result<Report> {
+    let (input_len, output_len) = compress_file(&input, &output, codec, level)?;
+
+    let value = json!({
+        "input": input.display().to_string(),
+        "output": output.display().to_string(),
+        "codec": codec.name(),
+        "input_len": input_len,
+        "output_len": output_len,
+    });
+    let text = format!(
+        "compressed {} -> {} ({} -> {} bytes, {})",
+        input.display(),
+        output.display(),
+        input_len,
+        output_len,
+        codec.name()
+    );
+    Ok(Report::ok(value, text))
+}
+
+fn decompress(codec: CompressionCodec, input: PathBuf, output: PathBuf) -> io::Result<Report> {
+    let (input_len, output_len) = decompress_file(&input, &output, codec)?;
+
+    let value = json!({
+        "input": input.display().to_string(),
+        "output": output.display().to_string(),
+        "codec": codec.name(),
+        "input_len": input_len,
+        "output_len": output_len,
+    });
+    let text = format!(
+        "decompressed {} -> {} ({} -> {} bytes, {})",
+        input.display(),
+        output.display(),
+        input_len,
+        output_len,
+        codec.name()
+    );
+    Ok(Report::ok(value, text))
+}
+
+fn profile(path: String) -> io::Result<Report> {
+    let profile = CompressionProfiler::default().for_path(&path);
+
+    let value = json!({
+        "path": path,
+        "profile": profile.name,
+        "codec": profile.codec.name(),
+        "level": profile.level,
+        "expected_ratio": profile.expected_ratio,
+        "description": profile.description,
+    });
+    let level = profile
+        .level
+        .map_or_else(|| "default".to_string(), |l| l.to_string());
+    let text = format!(
+        "{}: {} ({} level {}, expected ratio {:.2})\n  {}",
+        path,
+        profile.name,
+        profile.codec.name(),
+        level,
+        profile.expected_ratio,
+        profile.description
+    );
+    Ok(Report::ok(value, text))
+}
+
+fn verify(path: PathBuf) -> io::Result<Report> {
+    let data = std::fs::read(&path)?;
+
+    let (header, result) = match EnvelopeHeader::parse(&data) {
+        Ok(Some(header)) => (Some(header), unwrap_auto(header.kind, &data).map(|_| ())),
+        Ok(None) => (None, Ok(())),
+        Err(e) => (None, Err(e)),
+    };
+
+    let status = match (&header, &result) {
+        (_, Err(_)) => "corrupt",
+        (Some(_), Ok(())) => "ok",
+        (None, Ok(())) => "legacy",
+    };
+    let error = result.as_ref().err().map(|e| e.to_string());
+
+    let value = json!({
+        "path": path.display().to_string(),
+        "status": status,
+        "ok": result.is_ok(),
+        "header": header_json(header.as_ref()),
+        "error": error,
+    });
+    let text = match &error {
+        Some(e) => format!("{}: {} ({})", path.display(), status, e),
+        None => format!("{}: {}", path.display(), status),
+    };
+
+    Ok(Report {
+        value,
+        text,
+        success: result.is_ok(),
+    })
+}
+
+fn run(command: Command) -> io::Result<Report> {
+    match command {
+        Command::Inspect { path } => inspect(path),
+        Command::Wrap {
+            kind,
+            codec,
+            level,
+            input,
+            output,
+        } => wrap(kind, codec, level, input, output),
+        Command::Unwrap {
+            kind,
+            input,
+            output,
+        } => unwrap(kind, input, output),
+        Command::Compress {
+            codec,
+            level,
+            input,
+            output,
+        } => compress(codec, level, input, output),
+        Command::Decompress {
+            codec,
+            input,
+            output,
+        } => decompress(codec, input, output),
+        Command::Profile { path } => profile(path),
+        Command::Verify { path } => verify(path),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command) {
+        Ok(report) => {
+            if cli.json {
+                println!("{}", report.value);
+            } else {
+                println!("{}", report.text);
+            }
+            if report.success {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            if cli.json {
+                println!("{}", json!({ "error": e.to_string() }));
+            } else {
+                eprintln!("edn-io: {e}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Multi-entry archive container built on envelopes
+//!
+//! Bundles many named payloads (engrams, sub-engrams, ...) into a single file.
+//! Each entry is stored as an envelope with its own `PayloadKind` and a codec
+//! chosen by a `CompressionProfiler` from the entry name. A central directory
+//! at the end of the archive allows listing entries and reading any one of
+//! them without scanning the rest.
+//!
+//! ## Layout
+//!
+//! ```text
+//! +-----------------+  "EDNA" magic, u16 version, u16 reserved
+//! | header (8)      |
+//! +-----------------+
+//! | entry data ...  |  envelope bytes (raw bytes for uncompressed entries)
+//! +-----------------+
+//! | directory       |  bincode-encoded Vec<ArchiveEntry>
+//! +-----------------+
+//! | trailer (20)    |  u64 directory offset, u64 directory length, "EDNA"
+//! +-----------------+
+//! ```
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{ArchiveReader, ArchiveWriter, PayloadKind};
+//! use std::io::Cursor;
+//!
+//! let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+//! writer.add("root.engram", PayloadKind::EngramBincode, b"root").unwrap();
+//! writer.add("sub/0.engram", PayloadKind::SubEngramBincode, b"sub").unwrap();
+//! let bytes = writer.finish().unwrap();
+//!
+//! let mut reader = ArchiveReader::open(Cursor::new(bytes)).unwrap();
+//! assert_eq!(reader.entries().len(), 2);
+//! assert_eq!(reader.read("sub/0.engram").unwrap(), b"sub");
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::envelope::{
+    unwrap_auto, wrap_or_legacy, BinaryWriteOptions, CompressionCodec, EnvelopeHeader, PayloadKind,
+};
+use super::profiles::CompressionProfiler;
+use super::stream::StreamWriter;
+
+const ARCHIVE_MAGIC: [u8; 4] = *b"EDNA";
+const ARCHIVE_VERSION: u16 = 1;
+const ARCHIVE_HEADER_LEN: u64 = 8;
+const ARCHIVE_TRAILER_LEN: u64 = 20;
+
+/// Central directory record for one archive entry
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Entry name (unique within the archive)
+    pub name: String,
+    kind: u8,
+    codec: u8,
+    /// Offset of the stored bytes from the start of the archive
+    pub offset: u64,
+    /// Size of the stored (possibly compressed) bytes
+    pub stored_len: u64,
+    /// Size of the payload after decompression
+    pub raw_len: u64,
+}
+
+impl ArchiveEntry {
+    /// Payload kind of the entry
+    pub fn kind(&self) -> io::Result<PayloadKind> {
+        PayloadKind::from_u8(self.kind)
+            .ok_or_else(|| io::Error::other("unknown archive entry payload kind"))
+    }
+
+    /// Codec used to store the entry
+    pub fn codec(&self) -> io::Result<CompressionCodec> {
+        CompressionCodec::from_u8(self.codec)
+            .ok_or_else(|| io::Error::other("unknown archive entry compression codec"))
+    }
+}
+
+/// Writer for multi-entry archives
+///
+/// Entries are appended in order; the central directory is written by
+/// [`ArchiveWriter::finish`]. An archive that was never finished cannot be read.
+pub struct ArchiveWriter<W: Write> {
+    writer: StreamWriter<W>,
+    offset: u64,
+    entries: Vec<ArchiveEntry>,
+    names: HashMap<String, usize>,
+    profiler: CompressionProfiler,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Create an archive writer using the default compression profiler
+    pub fn new(writer: W) -> io::Result<Self> {
+        Self::with_profiler(writer, CompressionProfiler::default())
+    }
+
+    /// Create an archive writer with a custom compression profiler
+    pub fn with_profiler(writer: W, profiler: CompressionProfiler) -> io::Result<Self> {
+        let mut writer = StreamWriter::new(writer);
+        let mut header = [0u8; ARCHIVE_HEADER_LEN as usize];
+        header[..4].copy_from_slice(&ARCHIVE_MAGIC);
+        header[4..6].copy_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+        writer.write_chunk(&header)?;
+
+        Ok(Self {
+            writer,
+            offset: ARCHIVE_HEADER_LEN,
+            entries: Vec::new(),
+            names: HashMap::new(),
+            profiler,
+        })
+    }
+
+    /// Add an entry, choosing the codec from the entry name via the profiler
+    ///
+    /// If the profile's codec is not compiled in, the entry is stored uncompressed.
+    pub fn add(&mut self, name: &str, kind: PayloadKind, data: &[u8]) -> io::Result<()> {
+        let mut opts = self.profiler.for_path(name).to_write_options();
+        if !opts.codec.is_available() {
+            opts = BinaryWriteOptions::default();
+        }
+        self.add_with_options(name, kind, opts, data)
+    }
+
+    /// Add an entry with explicit write options
+    pub fn add_with_options(
+        &mut self,
+        name: &str,
+        kind: PayloadKind,
+        opts: BinaryWriteOptions,
+        data: &[u8],
+    ) -> io::Result<()> {
+        if self.names.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("duplicate archive entry `{name}`"),
+            ));
+        }
+
+        let stored = wrap_or_legacy(kind, opts, data)?;
+        self.writer.write_chunk(&stored)?;
+
+        self.names.insert(name.to_string(), self.entries.len());
+        self.entries.push(ArchiveEntry {
+            name: name.to_string(),
+            kind: kind as u8,
+            codec: opts.codec as u8,
+            offset: self.offset,
+            stored_len: stored.len() as u64,
+            raw_len: data.len() as u64,
+        });
+        self.offset += stored.len() as u64;
+        Ok(())
+    }
+
+    /// Add the contents of a file as an entry
+    pub fn add_file<P: AsRef<Path>>(
+        &mut self,
+        name: &str,
+        kind: PayloadKind,
+        path: P,
+    ) -> io::Result<()> {
+        let data = std::fs::read(path)?;
+        self.add(name, kind, &data)
+    }
+
+    /// Entries written so far
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Write the central directory and trailer, returning the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory = bincode::serialize(&self.entries).map_err(io::Error::other)?;
+        self.writer.write_chunk(&directory)?;
+
+        let mut trailer = Vec::with_capacity(ARCHIVE_TRAILER_LEN as usize);
+        trailer.extend_from_slice(&self.offset.to_le_bytes());
+        trailer.extend_from_slice(&(directory.len() as u64).to_le_bytes());
+        trailer.extend_from_slice(&ARCHIVE_MAGIC);
+        self.writer.write_chunk(&trailer)?;
+
+        self.writer.finish()
+    }
+}
+
+/// Random-access reader for archives written by [`ArchiveWriter`]
+pub struct ArchiveReader<R: Read + Seek> {
+    reader: R,
+    entries: Vec<ArchiveEntry>,
+    names: HashMap<String, usize>,
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    /// Open an archive, reading and validating its central directory
+    pub fn open(mut reader: R) -> io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        if len < ARCHIVE_HEADER_LEN + ARCHIVE_TRAILER_LEN {
+            return Err(invalid("archive too short"));
+        }
+
+        let mut header = [0u8; ARCHIVE_HEADER_LEN as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+        if header[..4] != ARCHIVE_MAGIC {
+            return Err(invalid("not an archive (bad magic)"));
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != ARCHIVE_VERSION {
+            return Err(invalid(&format!("unsupported archive version {version}")));
+        }
+
+        let mut trailer = [0u8; ARCHIVE_TRAILER_LEN as usize];
+        reader.seek(SeekFrom::Start(len - ARCHIVE_TRAILER_LEN))?;
+        reader.read_exact(&mut trailer)?;
+        if trailer[16..] != ARCHIVE_MAGIC {
+            return Err(invalid("archive trailer missing (unfinished archive?)"));
+        }
+        let dir_offset = u64::from_le_bytes(trailer[..8].try_into().expect("fixed slice"));
+        let dir_len = u64::from_le_bytes(trailer[8..16].try_into().expect("fixed slice"));
+        if dir_offset < ARCHIVE_HEADER_LEN
+            || dir_offset.checked_add(dir_len) != Some(len - ARCHIVE_TRAILER_LEN)
+        {
+            return Err(invalid("archive directory out of bounds"));
+        }
+
+        let mut directory = vec![0u8; dir_len as usize];
+        reader.seek(SeekFrom::Start(dir_offset))?;
+        reader.read_exact(&mut directory)?;
+        let entries: Vec<ArchiveEntry> =
+            bincode::deserialize(&directory).map_err(io::Error::other)?;
+
+        let mut names = HashMap::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let end = entry.offset.checked_add(entry.stored_len);
+            if entry.offset < ARCHIVE_HEADER_LEN || end.is_none_or(|end| end > dir_offset) {
+                return Err(invalid(&format!(
+                    "archive entry `{}` out of bounds",
+                    entry.name
+                )));
+            }
+            if names.insert(entry.name.clone(), i).is_some() {
+                return Err(invalid(&format!(
+                    "duplicate archive entry `{}`",
+                    entry.name
+                )));
+            }
+        }
+
+        Ok(Self {
+            reader,
+            entries,
+            names,
+        })
+    }
+
+    /// All entries in archive order
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Look up an entry by name
+    pub fn find(&self, name: &str) -> Option<&ArchiveEntry> {
+        self.names.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Read and decode the entry with the given name
+    pub fn read(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let entry = self.find(name).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("archive entry `{name}` not found"),
+            )
+        })?;
+        self.read_entry(&entry)
+    }
+
+    /// Read and decode a directory entry
+    pub fn read_entry(&mut self, entry: &ArchiveEntry) -> io::Result<Vec<u8>> {
+        let kind = entry.kind()?;
+        let codec = entry.codec()?;
+
+        let mut stored = Vec::with_capacity(entry.stored_len as usize);
+        self.reader.seek(SeekFrom::Start(entry.offset))?;
+        (&mut self.reader)
+            .take(entry.stored_len)
+            .read_to_end(&mut stored)?;
+        if stored.len() as u64 != entry.stored_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "archive entry truncated",
+            ));
+        }
+
+        let raw = if codec == CompressionCodec::None {
+            // Uncompressed entries are stored raw; never reinterpret them as envelopes
+            stored
+        } else {
+            match EnvelopeHeader::parse(&stored)? {
+                Some(header) if header.codec == codec => unwrap_auto(kind, &stored)?,
+                _ => return Err(invalid("archive entry does not match its directory record")),
+            }
+        };
+
+        if raw.len() as u64 != entry.raw_len {
+            return Err(invalid("archive entry size mismatch"));
+        }
+        Ok(raw)
+    }
+
+    /// Consume the reader, returning the underlying stream
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PROFILE_RUNTIME;
+    use std::io::Cursor;
+
+    fn uncompressed_writer() -> ArchiveWriter<Vec<u8>> {
+        ArchiveWriter::with_profiler(
+            Vec::new(),
+            CompressionProfiler::with_default(PROFILE_RUNTIME),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_archive_roundtrip_uncompressed() {
+        let mut writer = uncompressed_writer();
+        writer
+            .add("a.engram", PayloadKind::EngramBincode, b"alpha")
+            .unwrap();
+        // Raw payload that happens to look like an envelope must survive untouched
+        writer
+            .add(
+                "b.engram",
+                PayloadKind::SubEngramBincode,
+                b"EDN1 not a header",
+            )
+            .unwrap();
+        writer
+            .add("empty", PayloadKind::EngramBincode, b"")
+            .unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::open(Cursor::new(bytes)).unwrap();
+        let names: Vec<_> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.engram", "b.engram", "empty"]);
+
+        let entry = reader.find("b.engram").unwrap();
+        assert_eq!(entry.kind().unwrap(), PayloadKind::SubEngramBincode);
+        assert_eq!(entry.codec().unwrap(), CompressionCodec::None);
+
+        assert_eq!(reader.read("b.engram").unwrap(), b"EDN1 not a header");
+        assert_eq!(reader.read("a.engram").unwrap(), b"alpha");
+        assert!(reader.read("empty").unwrap().is_empty());
+        assert_eq!(
+            reader.read("missing").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_duplicate_entry_rejected() {
+        let mut writer = uncompressed_writer();
+        writer.add("x", PayloadKind::EngramBincode, b"1").unwrap();
+        let err = writer
+            .add("x", PayloadKind::EngramBincode, b"2")
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn test_unfinished_or_corrupt_archive() {
+        let mut writer = uncompressed_writer();
+        writer
+            .add("x", PayloadKind::EngramBincode, b"data")
+            .unwrap();
+        let mut bytes = writer.finish().unwrap();
+
+        // Truncated archive has no trailer
+        let truncated = bytes[..bytes.len() - 4].to_vec();
+        assert!(ArchiveReader::open(Cursor::new(truncated)).is_err());
+
+        // Directory offset pointing past the end
+        let len = bytes.len();
+        bytes[len - 20..len - 12].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(ArchiveReader::open(Cursor::new(bytes)).is_err());
+    }
+
+    #[cfg(all(feature = "compression-zstd", feature = "compression-lz4"))]
+    #[test]
+    fn test_profiler_selects_codec_per_entry() {
+        let data = b"archive entry contents ".repeat(100);
+
+        let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+        writer
+            .add("/boot/vmlinuz", PayloadKind::EngramBincode, &data)
+            .unwrap();
+        writer
+            .add("/etc/app.conf", PayloadKind::SubEngramBincode, &data)
+            .unwrap();
+        writer
+            .add("/tmp/scratch", PayloadKind::SubEngramBincode, &data)
+            .unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::open(Cursor::new(bytes)).unwrap();
+        let codecs: Vec<_> = reader
+            .entries()
+            .iter()
+            .map(|e| e.codec().unwrap())
+            .collect();
+        assert_eq!(
+            codecs,
+            [
+                CompressionCodec::Zstd,
+                CompressionCodec::Lz4,
+                CompressionCodec::None
+            ]
+        );
+
+        for name in ["/boot/vmlinuz", "/etc/app.conf", "/tmp/scratch"] {
+            assert_eq!(reader.read(name).unwrap(), data);
+        }
+        assert!(reader.find("/boot/vmlinuz").unwrap().stored_len < data.len() as u64);
+    }
+}
@@ -0,0 +1,470 @@
+//! Atomic file replacement
+//!
+//! Writing straight to the target path leaves a truncated file behind if the
+//! process dies midway, and `unwrap_auto` would accept a truncated raw file
+//! as a legacy payload. The file helpers therefore write to a temp file in
+//! the same directory, fsync it, rename it over the target and fsync the
+//! directory, so readers see either the old contents or the new ones.
+//!
+//! Every helper that writes a file has a `_with_options` variant taking
+//! [`FileWriteOptions`]; set `atomic: false` to write in place instead, and
+//! `durability` to choose how much is synced before the helper returns.
+//! A replaced file gets default permissions rather than those of the file it
+//! replaces.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::AtomicFile;
+//! use std::io::Write;
+//!
+//! let mut file = AtomicFile::create("engram.bin").unwrap();
+//! file.write_all(b"payload").unwrap();
+//! // Nothing is visible at engram.bin until the commit succeeds
+//! file.commit().unwrap();
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::durability::{Durability, Durable};
+
+/// Options shared by the file-writing helpers
+#[derive(Clone, Copy, Debug)]
+pub struct FileWriteOptions {
+    /// Write to a temp file and rename it into place (default: true)
+    pub atomic: bool,
+    /// Sync policy applied before the helper returns (default: [`Durability::Full`])
+    pub durability: Durability,
+}
+
+impl Default for FileWriteOptions {
+    fn default() -> Self {
+        Self {
+            atomic: true,
+            durability: Durability::Full,
+        }
+    }
+}
+
+/// File written under a temporary name and renamed over the target on commit
+///
+/// Dropping an uncommitted `AtomicFile` removes the temp file and leaves the
+/// target untouched.
+pub struct AtomicFile {
+    file: File,
+    tmp_path: PathBuf,
+    target: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Create a temp file next to `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let target = path.as_ref().to_path_buf();
+        let (file, tmp_path) = create_temp(&target)?;
+        Ok(Self {
+            file,
+            tmp_path,
+            target,
+            committed: false,
+        })
+    }
+
+    /// The underlying temp file
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Path the file will be renamed to
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Sync the temp file, rename it over the target and sync the directory
+    pub fn commit(self) -> io::Result<()> {
+        self.commit_with(Durability::Full)
+    }
+
+    /// Rename the temp file over the target, syncing according to `durability`
+    ///
+    /// With [`Durability::None`] the replacement is still atomic for readers
+    /// but may be lost on power failure.
+    pub fn commit_with(mut self, durability: Durability) -> io::Result<()> {
+        self.file.flush()?;
+        durability.sync_file(&self.file)?;
+        fs::rename(&self.tmp_path, &self.target)?;
+        self.committed = true;
+        if durability.syncs_dir() {
+            sync_parent_dir(&self.target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Durable for AtomicFile {
+    fn sync(&mut self, durability: Durability) -> io::Result<()> {
+        self.file.sync(durability)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// Atomically replace `path` with `data`
+pub fn write_atomic<P: AsRef<Path>>(path: P, data: &[u8]) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(data)?;
+    file.commit()
+}
+
+/// Output file for the helpers: atomic or written in place, per [`FileWriteOptions`]
+pub(crate) struct OutputFile {
+    inner: OutputInner,
+    durability: Durability,
+}
+
+enum OutputInner {
+    Atomic(AtomicFile),
+    Direct(File),
+}
+
+impl OutputFile {
+    pub(crate) fn create<P: AsRef<Path>>(path: P, opts: FileWriteOptions) -> io::Result<Self> {
+        let inner = if opts.atomic {
+            OutputInner::Atomic(AtomicFile::create(path)?)
+        } else {
+            OutputInner::Direct(File::create(path)?)
+        };
+        Ok(Self {
+            inner,
+            durability: opts.durability,
+        })
+    }
+
+    /// Finish the write; for atomic output this is the point the target is replaced
+    pub(crate) fn commit(self) -> io::Result<()> {
+        match self.inner {
+            OutputInner::Atomic(file) => file.commit_with(self.durability),
+            OutputInner::Direct(mut file) => file.sync(self.durability),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            OutputInner::Atomic(file) => file.write(buf),
+            OutputInner::Direct(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            OutputInner::Atomic(file) => file.flush(),
+            OutputInner::Direct(file) => file.flush(),
+        }
+    }
+}
+
+/// Directory holding `path` (`.` for a bare file name)
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    }
+}
+
+/// Temp path next to `target`: `.<name>.<pid>.<n>.tmp`
+fn temp_path(target: &Path) -> io::Result<PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    Ok(parent_dir(target).join(tmp_name))
+}
+
+fn create_temp(target: &Path) -> io::Result<(File, PathBuf)> {
+    loop {
+        let tmp_path = temp_path(target)?;
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((file, tmp_path)),
+            // Left over from an earlier process with the same pid
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Make a rename in the directory holding `path` durable
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(parent_dir(_path))?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+pub mod async_atomic {
+    //! Async atomic file replacement
+
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use tokio::fs::{File, OpenOptions};
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+    use super::super::durability::async_durability::AsyncDurable;
+    use super::super::durability::Durability;
+    use super::FileWriteOptions;
+
+    /// Async counterpart of [`AtomicFile`](super::AtomicFile)
+    pub struct AsyncAtomicFile {
+        file: File,
+        tmp_path: PathBuf,
+        target: PathBuf,
+        committed: bool,
+    }
+
+    impl AsyncAtomicFile {
+        /// Create a temp file next to `path`
+        pub async fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            let target = path.as_ref().to_path_buf();
+            loop {
+                let tmp_path = super::temp_path(&target)?;
+                match OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&tmp_path)
+                    .await
+                {
+                    Ok(file) => {
+                        return Ok(Self {
+                            file,
+                            tmp_path,
+                            target,
+                            committed: false,
+                        })
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        /// Sync the temp file, rename it over the target and sync the directory
+        pub async fn commit(self) -> io::Result<()> {
+            self.commit_with(Durability::Full).await
+        }
+
+        /// Rename the temp file over the target, syncing according to `durability`
+        pub async fn commit_with(mut self, durability: Durability) -> io::Result<()> {
+            self.file.flush().await?;
+            durability.sync_file_async(&self.file).await?;
+            tokio::fs::rename(&self.tmp_path, &self.target).await?;
+            self.committed = true;
+            if !durability.syncs_dir() {
+                return Ok(());
+            }
+            let target = self.target.clone();
+            tokio::task::spawn_blocking(move || super::sync_parent_dir(&target))
+                .await
+                .map_err(io::Error::other)?
+        }
+    }
+
+    impl AsyncWrite for AsyncAtomicFile {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.get_mut().file).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().file).poll_flush(cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().file).poll_shutdown(cx)
+        }
+    }
+
+    impl AsyncDurable for AsyncAtomicFile {
+        fn sync(
+            &mut self,
+            durability: Durability,
+        ) -> impl std::future::Future<Output = io::Result<()>> + Send {
+            self.file.sync(durability)
+        }
+    }
+
+    impl Drop for AsyncAtomicFile {
+        fn drop(&mut self) {
+            if !self.committed {
+                let _ = std::fs::remove_file(&self.tmp_path);
+            }
+        }
+    }
+
+    /// Atomically replace `path` with `data` (async)
+    pub async fn write_atomic<P: AsRef<Path>>(path: P, data: &[u8]) -> io::Result<()> {
+        let mut file = AsyncAtomicFile::create(path).await?;
+        file.write_all(data).await?;
+        file.commit().await
+    }
+
+    /// Async output file for the helpers, per [`FileWriteOptions`]
+    pub(crate) struct AsyncOutputFile {
+        inner: AsyncOutputInner,
+        durability: Durability,
+    }
+
+    enum AsyncOutputInner {
+        Atomic(AsyncAtomicFile),
+        Direct(File),
+    }
+
+    impl AsyncOutputFile {
+        pub(crate) async fn create<P: AsRef<Path>>(
+            path: P,
+            opts: FileWriteOptions,
+        ) -> io::Result<Self> {
+            let inner = if opts.atomic {
+                AsyncOutputInner::Atomic(AsyncAtomicFile::create(path).await?)
+            } else {
+                AsyncOutputInner::Direct(File::create(path).await?)
+            };
+            Ok(Self {
+                inner,
+                durability: opts.durability,
+            })
+        }
+
+        pub(crate) async fn commit(self) -> io::Result<()> {
+            match self.inner {
+                AsyncOutputInner::Atomic(file) => file.commit_with(self.durability).await,
+                AsyncOutputInner::Direct(mut file) => file.sync(self.durability).await,
+            }
+        }
+    }
+
+    impl AsyncWrite for AsyncOutputFile {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            match &mut self.get_mut().inner {
+                AsyncOutputInner::Atomic(file) => Pin::new(file).poll_write(cx, buf),
+                AsyncOutputInner::Direct(file) => Pin::new(file).poll_write(cx, buf),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().inner {
+                AsyncOutputInner::Atomic(file) => Pin::new(file).poll_flush(cx),
+                AsyncOutputInner::Direct(file) => Pin::new(file).poll_flush(cx),
+            }
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().inner {
+                AsyncOutputInner::Atomic(file) => Pin::new(file).poll_shutdown(cx),
+                AsyncOutputInner::Direct(file) => Pin::new(file).poll_shutdown(cx),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn dir_entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_commit_replaces_target() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("target.bin");
+        fs::write(&path, b"old").unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new contents").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        file.commit().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new contents");
+        assert_eq!(dir_entries(dir.path()), vec!["target.bin"]);
+    }
+
+    #[test]
+    fn test_drop_without_commit_keeps_target() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("target.bin");
+        fs::write(&path, b"old").unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"half written").unwrap();
+        drop(file);
+
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert_eq!(dir_entries(dir.path()), vec!["target.bin"]);
+    }
+
+    #[test]
+    fn test_direct_output_writes_in_place() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("direct.bin");
+
+        let mut out = OutputFile::create(
+            &path,
+            FileWriteOptions {
+                atomic: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        out.write_all(b"direct").unwrap();
+        out.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"direct");
+    }
+}
@@ -0,0 +1,474 @@
+//! Configurable bincode encoding
+//!
+//! [`to_bincode`](super::to_bincode) and friends use bincode's legacy
+//! defaults: fixed-width little-endian integers and no size limit, so a
+//! corrupt length prefix can make the decoder allocate without bound.
+//! [`BincodeConfig`] selects a byte limit, integer encoding and endianness;
+//! the `_with_config` helpers mirror the plain bincode helpers.
+//!
+//! `BincodeConfig::default()` is wire-compatible with the plain helpers and
+//! with existing files; only a limit is usually worth adding when reading
+//! untrusted data. Data written with varint or big-endian encoding must be
+//! read back with the same config.
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{from_bincode_with_config, to_bincode, BincodeConfig};
+//!
+//! let bytes = to_bincode(&vec![0u8; 1024]).unwrap();
+//! let config = BincodeConfig::default().with_limit(256);
+//! assert!(from_bincode_with_config::<Vec<u8>>(&bytes, config).is_err());
+//! ```
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::atomic::{FileWriteOptions, OutputFile};
+use super::buffer::DEFAULT_BUFFER_SIZE;
+use super::envelope::CompressionCodec;
+use super::stream_compress::{CompressionLevel, StreamCompressor, StreamDecompressor};
+
+/// Integer encoding used by bincode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntEncoding {
+    /// Fixed-width integers (bincode 1.x default, used by `to_bincode`)
+    #[default]
+    Fixint,
+    /// Variable-length integers, smaller for small values
+    Varint,
+}
+
+/// Byte order used by bincode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endianness {
+    /// Little-endian (default)
+    #[default]
+    Little,
+    /// Big-endian
+    Big,
+}
+
+/// Bincode encoding options
+///
+/// The default matches `bincode::serialize`/`bincode::deserialize`.
+/// Trailing bytes after a value are always allowed, as with the defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BincodeConfig {
+    /// Maximum number of bytes read or written per value (default: none)
+    pub limit: Option<u64>,
+    /// Integer encoding (default: fixint)
+    pub int_encoding: IntEncoding,
+    /// Byte order (default: little-endian)
+    pub endianness: Endianness,
+}
+
+impl BincodeConfig {
+    /// Limit values to `limit` bytes
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Use variable-length integer encoding
+    pub fn with_varint(mut self) -> Self {
+        self.int_encoding = IntEncoding::Varint;
+        self
+    }
+
+    /// Use big-endian byte order
+    pub fn with_big_endian(mut self) -> Self {
+        self.endianness = Endianness::Big;
+        self
+    }
+}
+
+/// Bind `$opts` to the bincode `Options` described by a [`BincodeConfig`]
+///
+/// bincode encodes its options in the type, so every combination gets its
+/// own expansion of `$body`.
+macro_rules! with_options {
+    ($config:expr, $opts:ident => $body:expr) => {{
+        use bincode::Options as _;
+        let config: BincodeConfig = $config;
+        let base = bincode::DefaultOptions::new().allow_trailing_bytes();
+        match config.int_encoding {
+            IntEncoding::Fixint => {
+                with_options!(@endian config, base.with_fixint_encoding(), $opts => $body)
+            }
+            IntEncoding::Varint => {
+                with_options!(@endian config, base.with_varint_encoding(), $opts => $body)
+            }
+        }
+    }};
+    (@endian $config:ident, $base:expr, $opts:ident => $body:expr) => {
+        match $config.endianness {
+            Endianness::Little => {
+                with_options!(@limit $config, $base.with_little_endian(), $opts => $body)
+            }
+            Endianness::Big => {
+                with_options!(@limit $config, $base.with_big_endian(), $opts => $body)
+            }
+        }
+    };
+    (@limit $config:ident, $base:expr, $opts:ident => $body:expr) => {
+        match $config.limit {
+            Some(limit) => {
+                let $opts = $base.with_limit(limit);
+                $body
+            }
+            None => {
+                let $opts = $base.with_no_limit();
+                $body
+            }
+        }
+    };
+}
+
+pub(crate) fn bincode_error(e: bincode::ErrorKind) -> io::Error {
+    match e {
+        bincode::ErrorKind::Io(e) => e,
+        bincode::ErrorKind::SizeLimit => {
+            io::Error::new(io::ErrorKind::InvalidData, "bincode size limit exceeded")
+        }
+        other => io::Error::other(other),
+    }
+}
+
+/// Serialize data to bincode with explicit options
+pub fn to_bincode_with_config<T: serde::Serialize>(
+    value: &T,
+    config: BincodeConfig,
+) -> io::Result<Vec<u8>> {
+    with_options!(config, opts => opts.serialize(value)).map_err(|e| bincode_error(*e))
+}
+
+/// Deserialize data from bincode with explicit options
+pub fn from_bincode_with_config<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+    config: BincodeConfig,
+) -> io::Result<T> {
+    // bincode only enforces the limit when decoding through a reader
+    with_options!(config, opts => opts.deserialize_from(bytes)).map_err(|e| bincode_error(*e))
+}
+
+/// Write data to a writer in bincode format with explicit options
+pub fn write_bincode_with_config<W: Write, T: serde::Serialize>(
+    writer: &mut W,
+    value: &T,
+    config: BincodeConfig,
+) -> io::Result<()> {
+    with_options!(config, opts => opts.serialize_into(writer, value)).map_err(|e| bincode_error(*e))
+}
+
+/// Read one value from a reader in bincode format with explicit options
+///
+/// Unlike [`read_bincode`](super::read_bincode), this decodes straight from
+/// the reader and stops after the value, so the limit also bounds how much
+/// is read.
+pub fn read_bincode_with_config<R: Read, T: serde::de::DeserializeOwned>(
+    reader: &mut R,
+    config: BincodeConfig,
+) -> io::Result<T> {
+    with_options!(config, opts => opts.deserialize_from(&mut *reader))
+        .map_err(|e| bincode_error(*e))
+}
+
+/// Write data to a file in bincode format with explicit options
+pub fn write_bincode_file_with_config<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    config: BincodeConfig,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let file = OutputFile::create(path, opts)?;
+    let mut writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, file);
+    write_bincode_with_config(&mut writer, value, config)?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Read data from a file in bincode format with explicit options
+pub fn read_bincode_file_with_config<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+    path: P,
+    config: BincodeConfig,
+) -> io::Result<T> {
+    let file = std::fs::File::open(path)?;
+    read_bincode_with_config(
+        &mut BufReader::with_capacity(DEFAULT_BUFFER_SIZE, file),
+        config,
+    )
+}
+
+/// Write a compressed bincode stream to a file with explicit options
+///
+/// Like [`write_bincode_compressed`](super::write_bincode_compressed), which
+/// is this function with `BincodeConfig::default()`.
+pub fn write_bincode_compressed_with_config<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    config: BincodeConfig,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let file = OutputFile::create(path, opts)?;
+    let writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, file);
+    let compressor = StreamCompressor::with_codec(writer, codec, level)?;
+    // bincode issues many small writes; batch them before the encoder
+    let mut buffered = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, compressor);
+    write_bincode_with_config(&mut buffered, value, config)?;
+    let compressor = buffered.into_inner().map_err(|e| e.into_error())?;
+    let writer = compressor.finish()?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Read a compressed bincode file with explicit options
+///
+/// The limit applies to the decompressed bincode stream, so it also bounds
+/// how much is decompressed.
+pub fn read_bincode_compressed_with_config<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+    path: P,
+    codec: CompressionCodec,
+    config: BincodeConfig,
+) -> io::Result<T> {
+    let file = std::fs::File::open(path)?;
+    let reader = BufReader::with_capacity(DEFAULT_BUFFER_SIZE, file);
+    let decompressor = StreamDecompressor::with_codec(reader, codec)?;
+    // bincode issues many small reads; keep them off the decoder
+    let mut decompressed = BufReader::with_capacity(DEFAULT_BUFFER_SIZE, decompressor);
+    read_bincode_with_config(&mut decompressed, config)
+}
+
+#[cfg(feature = "async")]
+pub mod async_bincode_config {
+    //! Async variants of the configured bincode helpers
+
+    use std::io;
+    use std::path::Path;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::super::async_serialize::{read_length_prefixed_limited, write_length_prefixed};
+    use super::super::atomic::async_atomic::AsyncOutputFile;
+    use super::super::atomic::FileWriteOptions;
+    use super::{from_bincode_with_config, to_bincode_with_config, BincodeConfig};
+
+    /// Write data to a file in bincode format with explicit options (async)
+    pub async fn write_bincode_file_with_config<P: AsRef<Path>, T: serde::Serialize>(
+        path: P,
+        value: &T,
+        config: BincodeConfig,
+        opts: FileWriteOptions,
+    ) -> io::Result<()> {
+        let bytes = to_bincode_with_config(value, config)?;
+        let mut file = AsyncOutputFile::create(path, opts).await?;
+        file.write_all(&bytes).await?;
+        file.commit().await
+    }
+
+    /// Read data from a file in bincode format with explicit options (async)
+    ///
+    /// With a limit set, at most `limit + 1` bytes of the file are read.
+    pub async fn read_bincode_file_with_config<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+        path: P,
+        config: BincodeConfig,
+    ) -> io::Result<T> {
+        let file = tokio::fs::File::open(path).await?;
+        read_bincode_with_config(&mut file.take(read_cap(config)), config).await
+    }
+
+    /// Write data to an async writer in bincode format with explicit options
+    pub async fn write_bincode_with_config<W: AsyncWriteExt + Unpin, T: serde::Serialize>(
+        writer: &mut W,
+        value: &T,
+        config: BincodeConfig,
+    ) -> io::Result<()> {
+        let bytes = to_bincode_with_config(value, config)?;
+        writer.write_all(&bytes).await
+    }
+
+    /// Read data from an async reader in bincode format with explicit options
+    ///
+    /// Reads to the end of the stream, or at most `limit + 1` bytes with a limit set.
+    pub async fn read_bincode_with_config<
+        R: AsyncReadExt + Unpin,
+        T: serde::de::DeserializeOwned,
+    >(
+        reader: &mut R,
+        config: BincodeConfig,
+    ) -> io::Result<T> {
+        let mut bytes = Vec::new();
+        reader
+            .take(read_cap(config))
+            .read_to_end(&mut bytes)
+            .await?;
+        from_bincode_with_config(&bytes, config)
+    }
+
+    /// Write a length-prefixed bincode value with explicit options
+    pub async fn write_bincode_prefixed_with_config<
+        W: AsyncWriteExt + Unpin,
+        T: serde::Serialize,
+    >(
+        writer: &mut W,
+        value: &T,
+        config: BincodeConfig,
+    ) -> io::Result<()> {
+        write_length_prefixed(writer, &to_bincode_with_config(value, config)?).await
+    }
+
+    /// Read a length-prefixed bincode value with explicit options; `Ok(None)` at end of stream
+    ///
+    /// A prefix larger than the limit is rejected before the value is read.
+    pub async fn read_bincode_prefixed_with_config<
+        R: AsyncReadExt + Unpin,
+        T: serde::de::DeserializeOwned,
+    >(
+        reader: &mut R,
+        config: BincodeConfig,
+    ) -> io::Result<Option<T>> {
+        match read_length_prefixed_limited(reader, config.limit).await? {
+            Some(bytes) => from_bincode_with_config(&bytes, config).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Bytes worth reading for one value: one past the limit, so overlong input still fails
+    fn read_cap(config: BincodeConfig) -> u64 {
+        config
+            .limit
+            .map_or(u64::MAX, |limit| limit.saturating_add(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Sample {
+        id: u64,
+        values: Vec<u32>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            id: 300,
+            values: vec![1, 2, 70_000],
+        }
+    }
+
+    #[test]
+    fn test_default_matches_plain_helpers() {
+        let bytes = to_bincode_with_config(&sample(), BincodeConfig::default()).unwrap();
+        assert_eq!(bytes, crate::io::to_bincode(&sample()).unwrap());
+        let decoded: Sample = from_bincode_with_config(&bytes, BincodeConfig::default()).unwrap();
+        assert_eq!(decoded, sample());
+    }
+
+    #[test]
+    fn test_varint_and_big_endian() {
+        let fixint = to_bincode_with_config(&sample(), BincodeConfig::default()).unwrap();
+        let varint = BincodeConfig::default().with_varint();
+        let compact = to_bincode_with_config(&sample(), varint).unwrap();
+        assert!(compact.len() < fixint.len());
+        assert_eq!(
+            from_bincode_with_config::<Sample>(&compact, varint).unwrap(),
+            sample()
+        );
+
+        let big = BincodeConfig::default().with_big_endian();
+        let bytes = to_bincode_with_config(&sample(), big).unwrap();
+        assert_eq!(&bytes[..8], &300u64.to_be_bytes());
+        let mut reader = &bytes[..];
+        assert_eq!(
+            read_bincode_with_config::<_, Sample>(&mut reader, big).unwrap(),
+            sample()
+        );
+    }
+
+    #[test]
+    fn test_limit_rejects_huge_length() {
+        // Claims a String of u64::MAX / 2 bytes
+        let mut malicious = (u64::MAX / 2).to_le_bytes().to_vec();
+        malicious.extend_from_slice(b"short");
+        let config = BincodeConfig::default().with_limit(1024);
+        let err = from_bincode_with_config::<String>(&malicious, config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let large = crate::io::to_bincode(&vec![0u64; 512]).unwrap();
+        let err = from_bincode_with_config::<Vec<u64>>(&large, config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = read_bincode_with_config::<_, Vec<u64>>(&mut &large[..], config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_compressed_file_with_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.bin");
+        let varint = BincodeConfig::default().with_varint();
+        let codec = if CompressionCodec::Zstd.is_available() {
+            CompressionCodec::Zstd
+        } else {
+            CompressionCodec::None
+        };
+
+        write_bincode_compressed_with_config(
+            &path,
+            &sample(),
+            codec,
+            CompressionLevel::Default,
+            varint,
+            FileWriteOptions::default(),
+        )
+        .unwrap();
+        let loaded: Sample = read_bincode_compressed_with_config(&path, codec, varint).unwrap();
+        assert_eq!(loaded, sample());
+
+        let large = vec![0u64; 4096];
+        write_bincode_compressed_with_config(
+            &path,
+            &large,
+            codec,
+            CompressionLevel::Default,
+            varint,
+            FileWriteOptions::default(),
+        )
+        .unwrap();
+        let err = read_bincode_compressed_with_config::<_, Vec<u64>>(
+            &path,
+            codec,
+            varint.with_limit(1024),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_prefixed_limit() {
+        use super::async_bincode_config::{
+            read_bincode_prefixed_with_config, write_bincode_prefixed_with_config,
+        };
+
+        let varint = BincodeConfig::default().with_varint();
+        let mut wire = Vec::new();
+        write_bincode_prefixed_with_config(&mut wire, &sample(), varint)
+            .await
+            .unwrap();
+        write_bincode_prefixed_with_config(&mut wire, &vec![0u8; 4096], varint)
+            .await
+            .unwrap();
+
+        let limited = varint.with_limit(1024);
+        let mut reader = &wire[..];
+        let first: Option<Sample> = read_bincode_prefixed_with_config(&mut reader, limited)
+            .await
+            .unwrap();
+        assert_eq!(first, Some(sample()));
+        let err = read_bincode_prefixed_with_config::<_, Vec<u8>>(&mut reader, limited)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -7,6 +7,11 @@ use std::fs::File;
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::Path;
 
+use super::atomic::{FileWriteOptions, OutputFile};
+use super::cdc::{CdcBuffer, CdcConfig};
+use super::pool::{BufferPool, PooledBuffer};
+use super::progress::IoHooks;
+
 /// Default buffer size for I/O operations (64KB)
 pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
 
@@ -43,6 +48,9 @@ pub fn buffered_reader_with_capacity<P: AsRef<Path>>(
 
 /// Create a buffered writer with default buffer size
 ///
+/// `flush` only hands data to the OS; call [`Durable::sync`](super::Durable::sync)
+/// to push it to stable storage.
+///
 /// # Examples
 /// ```no_run
 /// use embeddenator_io::buffered_writer;
@@ -81,26 +89,72 @@ pub fn buffered_writer_with_capacity<P: AsRef<Path>>(
 /// }).unwrap();
 /// println!("Total size: {} bytes", total_size);
 /// ```
-pub fn read_chunks<P, F>(path: P, chunk_size: usize, mut callback: F) -> io::Result<()>
+pub fn read_chunks<P, F>(path: P, chunk_size: usize, callback: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&[u8]) -> io::Result<()>,
+{
+    read_chunks_with_hooks(path, chunk_size, &IoHooks::default(), callback)
+}
+
+/// [`read_chunks`] with progress reporting and cancellation
+///
+/// Cancellation is checked before each chunk; progress is reported against the file size.
+pub fn read_chunks_with_hooks<P, F>(
+    path: P,
+    chunk_size: usize,
+    hooks: &IoHooks,
+    mut callback: F,
+) -> io::Result<()>
 where
     P: AsRef<Path>,
     F: FnMut(&[u8]) -> io::Result<()>,
 {
     let file = File::open(path)?;
+    let total = file.metadata()?.len();
     let mut reader = BufReader::with_capacity(chunk_size.max(4096), file);
     let mut buffer = vec![0u8; chunk_size];
+    let mut processed = 0u64;
 
     loop {
+        hooks.check()?;
         let n = reader.read(&mut buffer)?;
         if n == 0 {
             break;
         }
         callback(&buffer[..n])?;
+        processed += n as u64;
+        hooks.throttle(n as u64);
+        hooks.report(processed, Some(total));
     }
 
     Ok(())
 }
 
+/// Read a file in content-defined chunks, applying a function to each chunk
+///
+/// Chunk boundaries follow the data (see [`CdcConfig`]), so an insertion only
+/// changes the chunks around it.
+///
+/// # Examples
+/// ```no_run
+/// use embeddenator_io::{read_chunks_cdc, CdcConfig};
+///
+/// let mut chunks = 0;
+/// read_chunks_cdc("large_file.bin", CdcConfig::default(), |_chunk| {
+///     chunks += 1;
+///     Ok(())
+/// }).unwrap();
+/// ```
+pub fn read_chunks_cdc<P, F>(path: P, config: CdcConfig, callback: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&[u8]) -> io::Result<()>,
+{
+    let file = File::open(path)?;
+    ChunkStream::content_defined(file, config).process_all(callback)
+}
+
 /// Write data to a file in chunks
 ///
 /// # Examples
@@ -116,15 +170,28 @@ where
     I: IntoIterator<Item = D>,
     D: AsRef<[u8]>,
 {
-    let file = File::create(path)?;
+    write_chunks_with_options(path, chunks, FileWriteOptions::default())
+}
+
+/// Write data to a file in chunks with explicit file options
+pub fn write_chunks_with_options<P, I, D>(
+    path: P,
+    chunks: I,
+    opts: FileWriteOptions,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = D>,
+    D: AsRef<[u8]>,
+{
+    let file = OutputFile::create(path, opts)?;
     let mut writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, file);
 
     for chunk in chunks {
         writer.write_all(chunk.as_ref())?;
     }
 
-    writer.flush()?;
-    Ok(())
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
 }
 
 /// Copy data from reader to writer with buffering
@@ -145,26 +212,60 @@ pub fn copy_buffered<R: Read, W: Write>(
     reader: &mut R,
     writer: &mut W,
     buffer_size: usize,
+) -> io::Result<u64> {
+    copy_buffered_with_hooks(reader, writer, buffer_size, None, &IoHooks::default())
+}
+
+/// [`copy_buffered`] with progress reporting and cancellation
+///
+/// `total` is passed through to the progress observer when the source size is known.
+pub fn copy_buffered_with_hooks<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer_size: usize,
+    total: Option<u64>,
+    hooks: &IoHooks,
 ) -> io::Result<u64> {
     let mut buffer = vec![0u8; buffer_size];
-    let mut total = 0u64;
+    let mut copied = 0u64;
 
     loop {
+        hooks.check()?;
         let n = reader.read(&mut buffer)?;
         if n == 0 {
             break;
         }
         writer.write_all(&buffer[..n])?;
-        total += n as u64;
+        copied += n as u64;
+        hooks.throttle(n as u64);
+        hooks.report(copied, total);
     }
 
-    Ok(total)
+    Ok(copied)
 }
 
 /// Stream processor for chunked data processing
+///
+/// Cuts fixed-size chunks by default, or content-defined chunks when created
+/// with [`ChunkStream::content_defined`]. Also usable as an iterator of
+/// `io::Result<Vec<u8>>` chunks.
+///
+/// # Examples
+/// ```
+/// use embeddenator_io::ChunkStream;
+/// use std::io::Cursor;
+///
+/// let stream = ChunkStream::with_chunk_size(Cursor::new(vec![0u8; 100]), 30);
+/// let lens: Vec<usize> = stream.map(|chunk| chunk.unwrap().len()).collect();
+/// assert_eq!(lens, vec![30, 30, 30, 10]);
+/// ```
 pub struct ChunkStream<R> {
     reader: BufReader<R>,
     chunk_size: usize,
+    cdc: Option<CdcBuffer>,
+    pool: Option<BufferPool>,
+    // Set once iteration has returned `None` or an error
+    done: bool,
 }
 
 impl<R: Read> ChunkStream<R> {
@@ -178,13 +279,39 @@ impl<R: Read> ChunkStream<R> {
         Self {
             reader: BufReader::with_capacity(chunk_size.max(4096), reader),
             chunk_size,
+            cdc: None,
+            pool: None,
+            done: false,
+        }
+    }
+
+    /// Create a chunk stream that cuts content-defined chunks
+    pub fn content_defined(reader: R, config: CdcConfig) -> Self {
+        Self {
+            reader: BufReader::with_capacity(DEFAULT_BUFFER_SIZE, reader),
+            chunk_size: config.max_size(),
+            cdc: Some(CdcBuffer::new(config)),
+            pool: None,
+            done: false,
         }
     }
 
+    /// Take chunk buffers for [`next_pooled`](Self::next_pooled) from `pool`
+    pub fn with_pool(mut self, pool: BufferPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
     /// Read the next chunk
     ///
     /// Returns `None` when the end of the stream is reached.
     pub fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.cdc.is_some() {
+            return Ok(self
+                .next_cdc_range()?
+                .map(|range| self.cdc.as_ref().expect("cdc mode checked").data()[range].to_vec()));
+        }
+
         let mut buffer = vec![0u8; self.chunk_size];
         let n = self.reader.read(&mut buffer)?;
         if n == 0 {
@@ -194,13 +321,45 @@ impl<R: Read> ChunkStream<R> {
         Ok(Some(buffer))
     }
 
+    /// Read the next chunk into a buffer from the stream's pool
+    ///
+    /// The buffer goes back to the pool when dropped. Without
+    /// [`with_pool`](Self::with_pool), a pool sized to the chunk size is
+    /// created on first use.
+    pub fn next_pooled(&mut self) -> io::Result<Option<PooledBuffer>> {
+        let mut buffer = self
+            .pool
+            .get_or_insert_with(|| BufferPool::new(self.chunk_size))
+            .get();
+
+        if self.cdc.is_some() {
+            return Ok(self.next_cdc_range()?.map(|range| {
+                buffer
+                    .extend_from_slice(&self.cdc.as_ref().expect("cdc mode checked").data()[range]);
+                buffer
+            }));
+        }
+
+        if buffer.read_from(&mut self.reader, self.chunk_size)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(buffer))
+    }
+
     /// Process all chunks with a callback
     pub fn process_all<F>(&mut self, mut callback: F) -> io::Result<()>
     where
         F: FnMut(&[u8]) -> io::Result<()>,
     {
+        if self.cdc.is_some() {
+            while let Some(range) = self.next_cdc_range()? {
+                callback(&self.cdc.as_ref().expect("cdc mode checked").data()[range])?;
+            }
+            return Ok(());
+        }
+
+        let mut buffer = vec![0u8; self.chunk_size];
         loop {
-            let mut buffer = vec![0u8; self.chunk_size];
             let n = self.reader.read(&mut buffer)?;
             if n == 0 {
                 break;
@@ -209,17 +368,45 @@ impl<R: Read> ChunkStream<R> {
         }
         Ok(())
     }
+
+    fn next_cdc_range(&mut self) -> io::Result<Option<std::ops::Range<usize>>> {
+        let cdc = self.cdc.as_mut().expect("cdc mode checked");
+        while cdc.needs_fill() {
+            let n = self.reader.read(cdc.fill_slot())?;
+            cdc.filled(n);
+        }
+        Ok(cdc.next_range())
+    }
 }
 
+impl<R: Read> Iterator for ChunkStream<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let chunk = self.next_chunk().transpose();
+        // Stop after the first error so a failing reader is not retried forever
+        self.done = !matches!(chunk, Some(Ok(_)));
+        chunk
+    }
+}
+
+impl<R: Read> std::iter::FusedIterator for ChunkStream<R> {}
+
 #[cfg(feature = "async")]
 pub mod async_buffer {
     //! Async variants of buffering functions
 
+    use futures::stream::{self, Stream};
     use std::io;
     use std::path::Path;
     use tokio::fs::File;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
 
+    use super::super::cdc::{CdcBuffer, CdcConfig};
+    use super::super::progress::IoHooks;
     use super::DEFAULT_BUFFER_SIZE;
 
     /// Create an async buffered reader
@@ -235,9 +422,20 @@ pub mod async_buffer {
     }
 
     /// Read a file in chunks asynchronously
-    pub async fn read_chunks<P, F, Fut>(
+    pub async fn read_chunks<P, F, Fut>(path: P, chunk_size: usize, callback: F) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Vec<u8>) -> Fut,
+        Fut: std::future::Future<Output = io::Result<()>>,
+    {
+        read_chunks_with_hooks(path, chunk_size, &IoHooks::default(), callback).await
+    }
+
+    /// Read a file in chunks asynchronously, with progress reporting and cancellation
+    pub async fn read_chunks_with_hooks<P, F, Fut>(
         path: P,
         chunk_size: usize,
+        hooks: &IoHooks,
         mut callback: F,
     ) -> io::Result<()>
     where
@@ -246,43 +444,153 @@ pub mod async_buffer {
         Fut: std::future::Future<Output = io::Result<()>>,
     {
         let file = File::open(path).await?;
+        let total = file.metadata().await?.len();
         let mut reader = BufReader::with_capacity(chunk_size.max(4096), file);
         let mut buffer = vec![0u8; chunk_size];
+        let mut processed = 0u64;
 
         loop {
+            hooks.check()?;
             let n = reader.read(&mut buffer).await?;
             if n == 0 {
                 break;
             }
             callback(buffer[..n].to_vec()).await?;
+            processed += n as u64;
+            hooks.throttle_async(n as u64).await;
+            hooks.report(processed, Some(total));
         }
 
         Ok(())
     }
 
+    /// Read a file in content-defined chunks asynchronously
+    pub async fn read_chunks_cdc<P, F, Fut>(
+        path: P,
+        config: CdcConfig,
+        mut callback: F,
+    ) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Vec<u8>) -> Fut,
+        Fut: std::future::Future<Output = io::Result<()>>,
+    {
+        let file = File::open(path).await?;
+        let mut reader = BufReader::with_capacity(DEFAULT_BUFFER_SIZE, file);
+        let mut cdc = CdcBuffer::new(config);
+
+        loop {
+            while cdc.needs_fill() {
+                let n = reader.read(cdc.fill_slot()).await?;
+                cdc.filled(n);
+            }
+            match cdc.next_range() {
+                Some(range) => callback(cdc.data()[range].to_vec()).await?,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stream of fixed-size chunks read from `reader`
+    ///
+    /// The stream ends after the first error.
+    ///
+    /// # Examples
+    /// ```
+    /// use embeddenator_io::async_buffer::chunk_stream;
+    /// use futures::{StreamExt, TryStreamExt};
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let data = vec![7u8; 10_000];
+    /// let lens: Vec<usize> = chunk_stream(&data[..], 4096)
+    ///     .map_ok(|chunk| async move { Ok::<_, std::io::Error>(chunk.len()) })
+    ///     .try_buffer_unordered(4)
+    ///     .try_collect()
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(lens.iter().sum::<usize>(), 10_000);
+    /// # });
+    /// ```
+    pub fn chunk_stream<R>(reader: R, chunk_size: usize) -> impl Stream<Item = io::Result<Vec<u8>>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        stream::try_unfold(reader, move |mut reader| async move {
+            let mut buffer = vec![0u8; chunk_size];
+            let n = reader.read(&mut buffer).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            buffer.truncate(n);
+            Ok(Some((buffer, reader)))
+        })
+    }
+
+    /// Stream of content-defined chunks read from `reader`
+    ///
+    /// Boundaries match [`ChunkStream::content_defined`](super::ChunkStream::content_defined).
+    pub fn chunk_stream_cdc<R>(
+        reader: R,
+        config: CdcConfig,
+    ) -> impl Stream<Item = io::Result<Vec<u8>>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let state = (reader, CdcBuffer::new(config));
+        stream::try_unfold(state, |(mut reader, mut cdc)| async move {
+            while cdc.needs_fill() {
+                let n = reader.read(cdc.fill_slot()).await?;
+                cdc.filled(n);
+            }
+            Ok(cdc
+                .next_range()
+                .map(|range| (cdc.data()[range].to_vec(), (reader, cdc))))
+        })
+    }
+
     /// Copy data asynchronously with buffering
     pub async fn copy_buffered<R, W>(
         reader: &mut R,
         writer: &mut W,
         buffer_size: usize,
     ) -> io::Result<u64>
+    where
+        R: AsyncReadExt + Unpin,
+        W: AsyncWriteExt + Unpin,
+    {
+        copy_buffered_with_hooks(reader, writer, buffer_size, None, &IoHooks::default()).await
+    }
+
+    /// Copy data asynchronously with progress reporting and cancellation
+    pub async fn copy_buffered_with_hooks<R, W>(
+        reader: &mut R,
+        writer: &mut W,
+        buffer_size: usize,
+        total: Option<u64>,
+        hooks: &IoHooks,
+    ) -> io::Result<u64>
     where
         R: AsyncReadExt + Unpin,
         W: AsyncWriteExt + Unpin,
     {
         let mut buffer = vec![0u8; buffer_size];
-        let mut total = 0u64;
+        let mut copied = 0u64;
 
         loop {
+            hooks.check()?;
             let n = reader.read(&mut buffer).await?;
             if n == 0 {
                 break;
             }
             writer.write_all(&buffer[..n]).await?;
-            total += n as u64;
+            copied += n as u64;
+            hooks.throttle_async(n as u64).await;
+            hooks.report(copied, total);
         }
 
-        Ok(total)
+        Ok(copied)
     }
 }
 
@@ -291,6 +599,80 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    #[test]
+    fn test_chunk_stream_next_pooled_reuses_buffers() {
+        let data: Vec<u8> = (0..100u8).collect();
+        let pool = BufferPool::new(16);
+        let mut stream =
+            ChunkStream::with_chunk_size(Cursor::new(&data), 16).with_pool(pool.clone());
+
+        let mut out = Vec::new();
+        while let Some(chunk) = stream.next_pooled().unwrap() {
+            out.extend_from_slice(&chunk);
+        }
+        assert_eq!(out, data);
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn test_chunk_stream_iterator() {
+        let data: Vec<u8> = (0..50u8).collect();
+        let stream = ChunkStream::with_chunk_size(Cursor::new(&data), 8);
+
+        let first_two: Vec<Vec<u8>> = stream
+            .take_while(|chunk| chunk.is_ok())
+            .take(2)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(first_two, vec![data[..8].to_vec(), data[8..16].to_vec()]);
+
+        let all: Vec<u8> = ChunkStream::with_chunk_size(Cursor::new(&data), 8)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap()
+            .concat();
+        assert_eq!(all, data);
+    }
+
+    #[test]
+    fn test_chunk_stream_iterator_stops_after_error() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("device error"))
+            }
+        }
+
+        let mut stream = ChunkStream::new(Failing);
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+        assert_eq!(ChunkStream::new(Failing).count(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_chunk_streams() {
+        use super::async_buffer::{chunk_stream, chunk_stream_cdc};
+        use futures::TryStreamExt;
+
+        let data: Vec<u8> = (0..200_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+
+        let fixed: Vec<Vec<u8>> = chunk_stream(&data[..], 4096).try_collect().await.unwrap();
+        assert_eq!(fixed.len(), 49);
+        assert_eq!(fixed.concat(), data);
+
+        let config = CdcConfig::new(2048, 8192, 32 * 1024).unwrap();
+        let cdc: Vec<Vec<u8>> = chunk_stream_cdc(&data[..], config)
+            .try_collect()
+            .await
+            .unwrap();
+        let expected: Vec<Vec<u8>> = ChunkStream::content_defined(Cursor::new(&data), config)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(cdc, expected);
+    }
+
     #[test]
     fn test_chunk_stream() {
         let data = b"Hello, world! This is a test.";
@@ -333,4 +715,52 @@ mod tests {
 
         assert_eq!(total_bytes, data.len());
     }
+
+    #[test]
+    fn test_copy_buffered_cancellation() {
+        use crate::{is_cancelled, CancellationToken};
+
+        let token = CancellationToken::new();
+        let stop = token.clone();
+        let hooks = IoHooks::new()
+            .with_progress(move |done: u64, _total: Option<u64>| {
+                if done >= 2048 {
+                    stop.cancel();
+                }
+            })
+            .with_cancellation(token);
+
+        let data = vec![0u8; 8192];
+        let mut out = Vec::new();
+        let err = copy_buffered_with_hooks(&mut &data[..], &mut out, 1024, Some(8192), &hooks)
+            .unwrap_err();
+        assert!(is_cancelled(&err));
+        assert_eq!(out.len(), 2048);
+    }
+
+    #[test]
+    fn test_content_defined_chunk_stream() {
+        let data: Vec<u8> = (0..50_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        let config = CdcConfig::new(256, 1024, 4096).unwrap();
+        let expected: Vec<Vec<u8>> = config.chunks(&data).map(<[u8]>::to_vec).collect();
+
+        let mut stream = ChunkStream::content_defined(Cursor::new(&data), config);
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next_chunk().unwrap() {
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks, expected);
+
+        let mut stream = ChunkStream::content_defined(Cursor::new(&data), config);
+        let mut sizes = Vec::new();
+        stream
+            .process_all(|chunk| {
+                sizes.push(chunk.len());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(sizes, expected.iter().map(Vec::len).collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,315 @@
+//! Content-defined chunking (FastCDC)
+//!
+//! Fixed-size chunking shifts every chunk boundary after an insertion, which
+//! defeats deduplication between versions of the same data. Content-defined
+//! chunking picks boundaries from a rolling Gear hash of the data itself, so
+//! boundaries move with the content and unchanged regions produce identical
+//! chunks.
+//!
+//! This implements FastCDC with normalized chunking: a stricter mask is used
+//! before the average size and a looser one after it, which keeps chunk sizes
+//! close to the configured average.
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{CdcConfig, ChunkStream};
+//! use std::io::Cursor;
+//!
+//! let data: Vec<u8> = (0..200_000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+//! let config = CdcConfig::new(2 * 1024, 8 * 1024, 32 * 1024).unwrap();
+//! let mut stream = ChunkStream::content_defined(Cursor::new(&data), config);
+//!
+//! let mut total = 0;
+//! stream.process_all(|chunk| {
+//!     assert!(chunk.len() <= 32 * 1024);
+//!     total += chunk.len();
+//!     Ok(())
+//! }).unwrap();
+//! assert_eq!(total, data.len());
+//! ```
+
+use std::io;
+use std::ops::Range;
+
+/// Gear hash table: one pseudo-random 64-bit value per byte value
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    // splitmix64 with a fixed seed so chunk boundaries are stable across builds
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6564_6e5f_6364_6331;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Mask with `bits` set bits in the high end of the word
+///
+/// The Gear hash shifts left, so the high bits depend on the most bytes.
+const fn high_mask(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits)
+    }
+}
+
+/// Chunk size limits for content-defined chunking
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CdcConfig {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Default for CdcConfig {
+    /// 16KB minimum, 64KB average, 256KB maximum
+    fn default() -> Self {
+        Self::new(16 * 1024, 64 * 1024, 256 * 1024).expect("valid default CDC sizes")
+    }
+}
+
+impl CdcConfig {
+    /// Create a configuration from minimum, average and maximum chunk sizes
+    ///
+    /// Requires `0 < min_size <= avg_size <= max_size`. The average is a
+    /// target; the actual distribution depends on the data.
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> io::Result<Self> {
+        if min_size == 0 || min_size > avg_size || avg_size > max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "CDC sizes must satisfy 0 < min <= avg <= max",
+            ));
+        }
+
+        // Normalization level 2: two extra bits before the average, two fewer after
+        let bits = avg_size.ilog2();
+        Ok(Self {
+            min_size,
+            avg_size,
+            max_size,
+            mask_small: high_mask((bits + 2).min(64)),
+            mask_large: high_mask(bits.saturating_sub(2)),
+        })
+    }
+
+    /// Minimum chunk size (the last chunk of a stream may be smaller)
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// Target average chunk size
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    /// Maximum chunk size
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Length of the first chunk in `data`
+    ///
+    /// `data` must hold at least `max_size` bytes unless it is the end of the
+    /// stream; otherwise the returned boundary may differ from the one found
+    /// with more data available.
+    pub fn cut_point(&self, data: &[u8]) -> usize {
+        let len = data.len();
+        if len <= self.min_size {
+            return len;
+        }
+
+        let max = len.min(self.max_size);
+        let normal = max.min(self.avg_size);
+        let mut hash = 0u64;
+        let mut i = self.min_size;
+
+        while i < normal {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_small == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < max {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_large == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        max
+    }
+
+    /// Split an in-memory buffer into content-defined chunks
+    pub fn chunks<'a>(&self, data: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let config = *self;
+        let mut rest = data;
+        std::iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+            let (chunk, tail) = rest.split_at(config.cut_point(rest));
+            rest = tail;
+            Some(chunk)
+        })
+    }
+}
+
+/// Window buffer used by streaming readers to find chunk boundaries
+///
+/// Callers alternate between filling [`CdcBuffer::fill_slot`] from their
+/// reader (sync or async) and taking ranges from [`CdcBuffer::next_range`].
+pub(crate) struct CdcBuffer {
+    config: CdcConfig,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+
+impl CdcBuffer {
+    pub(crate) fn new(config: CdcConfig) -> Self {
+        Self {
+            config,
+            buf: vec![0u8; config.max_size],
+            start: 0,
+            end: 0,
+            eof: false,
+        }
+    }
+
+    /// Whether more input is needed before the next boundary can be found
+    pub(crate) fn needs_fill(&self) -> bool {
+        !self.eof && self.end - self.start < self.config.max_size
+    }
+
+    /// Free space to read into, after moving pending bytes to the front
+    pub(crate) fn fill_slot(&mut self) -> &mut [u8] {
+        if self.start > 0 {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        &mut self.buf[self.end..]
+    }
+
+    /// Record `n` bytes read into the fill slot (`0` marks end of stream)
+    pub(crate) fn filled(&mut self, n: usize) {
+        if n == 0 {
+            self.eof = true;
+        }
+        self.end += n;
+    }
+
+    /// Range of the next chunk within [`CdcBuffer::data`]
+    pub(crate) fn next_range(&mut self) -> Option<Range<usize>> {
+        if self.start == self.end {
+            return None;
+        }
+        let len = self.config.cut_point(&self.buf[self.start..self.end]);
+        let range = self.start..self.start + len;
+        self.start += len;
+        Some(range)
+    }
+
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(CdcConfig::new(0, 64, 128).is_err());
+        assert!(CdcConfig::new(128, 64, 256).is_err());
+        assert!(CdcConfig::new(16, 64, 32).is_err());
+        assert!(CdcConfig::new(64, 64, 64).is_ok());
+    }
+
+    #[test]
+    fn test_chunk_sizes_within_bounds() {
+        let config = CdcConfig::new(1024, 4096, 16384).unwrap();
+        let data = pseudo_random(500_000, 42);
+
+        let chunks: Vec<_> = config.chunks(&data).collect();
+        let (last, body) = chunks.split_last().unwrap();
+        assert!(body.iter().all(|c| c.len() >= 1024 && c.len() <= 16384));
+        assert!(last.len() <= 16384);
+        assert_eq!(chunks.concat(), data);
+
+        // Average should land in the neighbourhood of the target
+        let avg = data.len() / chunks.len();
+        assert!((2048..=8192).contains(&avg), "average chunk size {avg}");
+    }
+
+    #[test]
+    fn test_boundaries_resync_after_insert() {
+        let config = CdcConfig::new(256, 1024, 4096).unwrap();
+        let original = pseudo_random(100_000, 7);
+        let mut edited = original.clone();
+        edited.insert(10, 0xAA);
+
+        let a: std::collections::HashSet<_> = config.chunks(&original).collect();
+        let b: Vec<_> = config.chunks(&edited).collect();
+        let shared = b.iter().filter(|c| a.contains(*c)).count();
+
+        // Only the chunks around the edit should differ
+        assert!(
+            shared + 3 >= b.len(),
+            "{shared} of {} chunks shared",
+            b.len()
+        );
+    }
+
+    #[test]
+    fn test_buffer_matches_in_memory_chunking() {
+        let config = CdcConfig::new(128, 512, 2048).unwrap();
+        let data = pseudo_random(20_000, 99);
+        let expected: Vec<_> = config.chunks(&data).map(<[u8]>::to_vec).collect();
+
+        let mut buffer = CdcBuffer::new(config);
+        let mut input = &data[..];
+        let mut chunks = Vec::new();
+        loop {
+            while buffer.needs_fill() {
+                let slot = buffer.fill_slot();
+                // Feed in small pieces to exercise refilling
+                let n = slot.len().min(input.len()).min(300);
+                slot[..n].copy_from_slice(&input[..n]);
+                input = &input[n..];
+                buffer.filled(n);
+            }
+            match buffer.next_range() {
+                Some(range) => chunks.push(buffer.data()[range].to_vec()),
+                None => break,
+            }
+        }
+
+        assert_eq!(chunks, expected);
+    }
+}
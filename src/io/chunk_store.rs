@@ -0,0 +1,545 @@
+//! Content-addressed chunk store with deduplication
+//!
+//! Files are split into content-defined chunks (see [`CdcConfig`]), and each
+//! chunk is stored once under its BLAKE3 hash as a [`PayloadKind::Chunk`]
+//! envelope. A stored file is a bincode [`ChunkManifest`] listing its chunk
+//! hashes, so successive snapshots of mostly unchanged data only add the
+//! chunks that actually changed.
+//!
+//! ## Layout
+//!
+//! ```text
+//! <root>/chunks/ab/cdef...   chunk envelopes, named by hex hash
+//! <root>/manifests/<hex>     bincode ChunkManifest, named by the BLAKE3 hash of the file name
+//! <root>/refs                bincode reference counts (rebuilt from manifests if missing)
+//! ```
+//!
+//! Chunks, manifests and the reference table are written atomically. Chunks
+//! are written before the manifest that references them, so a crash can leave
+//! unreferenced chunks behind but never a manifest with missing chunks;
+//! [`ChunkStore::gc`] removes the leftovers. A store must only be opened by
+//! one process at a time.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::ChunkStore;
+//!
+//! let mut store = ChunkStore::open("store/").unwrap();
+//! let stats = store.put_file("corpus-v2.bin", "corpus-v2.bin").unwrap();
+//! println!("{} of {} chunks were new", stats.new_chunks, stats.chunks);
+//!
+//! store.remove("corpus-v1.bin").unwrap();
+//! let gc = store.gc().unwrap();
+//! println!("freed {} bytes", gc.bytes_freed);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::atomic::{write_atomic, AtomicFile};
+use super::buffer::{ChunkStream, DEFAULT_BUFFER_SIZE};
+use super::cdc::CdcConfig;
+use super::envelope::{unwrap_auto, wrap, BinaryWriteOptions, EnvelopeHeader, PayloadKind};
+use super::serialize::{from_bincode, to_bincode};
+
+const CHUNKS_DIR: &str = "chunks";
+const MANIFESTS_DIR: &str = "manifests";
+const REFS_FILE: &str = "refs";
+
+/// BLAKE3 hash identifying a chunk
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChunkHash(pub [u8; 32]);
+
+impl ChunkHash {
+    /// Hash `data`
+    pub fn of(data: &[u8]) -> Self {
+        Self(*blake3::hash(data).as_bytes())
+    }
+
+    /// Lowercase hex encoding
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.0)
+    }
+
+    /// Parse a 64-character hex string
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let bytes = hex_decode(s)?;
+        Some(Self(bytes.try_into().ok()?))
+    }
+}
+
+impl fmt::Display for ChunkHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for ChunkHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ChunkHash({})", self.to_hex())
+    }
+}
+
+/// One chunk of a stored file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkRef {
+    /// Hash of the uncompressed chunk
+    pub hash: ChunkHash,
+    /// Uncompressed chunk length
+    pub len: u64,
+}
+
+/// Ordered list of chunks making up a stored file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// Name the file is stored under
+    pub name: String,
+    /// Total file size in bytes
+    pub size: u64,
+    /// Chunks in file order (a chunk may appear more than once)
+    pub chunks: Vec<ChunkRef>,
+}
+
+/// Options for a [`ChunkStore`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChunkStoreOptions {
+    /// Codec and level used for newly written chunks
+    pub write: BinaryWriteOptions,
+    /// Chunk size limits
+    pub cdc: CdcConfig,
+}
+
+/// Result of storing a file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PutStats {
+    /// File size in bytes
+    pub size: u64,
+    /// Chunks in the file
+    pub chunks: u64,
+    /// Chunks that were not already in the store
+    pub new_chunks: u64,
+    /// Bytes written to disk for the new chunks
+    pub stored_bytes: u64,
+}
+
+/// Result of a garbage collection run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Unreferenced chunks deleted
+    pub chunks_removed: u64,
+    /// On-disk bytes freed
+    pub bytes_freed: u64,
+}
+
+/// Local content-addressed chunk store
+pub struct ChunkStore {
+    root: PathBuf,
+    opts: ChunkStoreOptions,
+    refs: HashMap<ChunkHash, u64>,
+}
+
+impl ChunkStore {
+    /// Open (or create) a store at `root` with default options
+    pub fn open<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        Self::with_options(root, ChunkStoreOptions::default())
+    }
+
+    /// Open (or create) a store at `root`
+    pub fn with_options<P: AsRef<Path>>(root: P, opts: ChunkStoreOptions) -> io::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join(CHUNKS_DIR))?;
+        fs::create_dir_all(root.join(MANIFESTS_DIR))?;
+
+        let mut store = Self {
+            root,
+            opts,
+            refs: HashMap::new(),
+        };
+        store.refs = match fs::read(store.root.join(REFS_FILE)) {
+            Ok(bytes) => from_bincode::<Vec<(ChunkHash, u64)>>(&bytes)?
+                .into_iter()
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => store.count_refs()?,
+            Err(e) => return Err(e),
+        };
+        Ok(store)
+    }
+
+    /// Root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Store everything read from `reader` under `name`, replacing any previous file
+    pub fn put<R: Read>(&mut self, name: &str, reader: R) -> io::Result<PutStats> {
+        let previous = match self.manifest(name) {
+            Ok(previous) => Some(previous),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut stats = PutStats::default();
+        let mut manifest = ChunkManifest {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        let mut stream = ChunkStream::content_defined(reader, self.opts.cdc);
+        while let Some(chunk) = stream.next_chunk()? {
+            let hash = ChunkHash::of(&chunk);
+            let path = self.chunk_path(&hash);
+            if !path.exists() {
+                let envelope = wrap(PayloadKind::Chunk, self.opts.write, &chunk)?;
+                fs::create_dir_all(path.parent().expect("chunk path has a parent"))?;
+                write_atomic(&path, &envelope)?;
+                stats.new_chunks += 1;
+                stats.stored_bytes += envelope.len() as u64;
+            }
+            let len = chunk.len() as u64;
+            manifest.chunks.push(ChunkRef { hash, len });
+            manifest.size += len;
+        }
+        stats.size = manifest.size;
+        stats.chunks = manifest.chunks.len() as u64;
+
+        write_atomic(self.manifest_path(name), &to_bincode(&manifest)?)?;
+
+        for chunk in &manifest.chunks {
+            *self.refs.entry(chunk.hash).or_insert(0) += 1;
+        }
+        if let Some(previous) = previous {
+            self.release(&previous);
+        }
+        self.save_refs()?;
+
+        Ok(stats)
+    }
+
+    /// Store the file at `path` under `name`
+    pub fn put_file<P: AsRef<Path>>(&mut self, name: &str, path: P) -> io::Result<PutStats> {
+        self.put(name, File::open(path)?)
+    }
+
+    /// Write the file stored under `name` to `writer`, returning its size
+    ///
+    /// Every chunk is checked against its hash before it is written.
+    pub fn get<W: Write>(&self, name: &str, writer: &mut W) -> io::Result<u64> {
+        let manifest = self.manifest(name)?;
+        for chunk in &manifest.chunks {
+            writer.write_all(&self.read_chunk(&chunk.hash)?)?;
+        }
+        Ok(manifest.size)
+    }
+
+    /// Restore the file stored under `name` to `path`, returning its size
+    pub fn get_file<P: AsRef<Path>>(&self, name: &str, path: P) -> io::Result<u64> {
+        let mut writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, AtomicFile::create(path)?);
+        let size = self.get(name, &mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.commit()?;
+        Ok(size)
+    }
+
+    /// Manifest of the file stored under `name`
+    pub fn manifest(&self, name: &str) -> io::Result<ChunkManifest> {
+        let manifest: ChunkManifest = from_bincode(&fs::read(self.manifest_path(name))?)?;
+        if manifest.name != name {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("manifest for {name:?} names {:?}", manifest.name),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    /// Whether a file is stored under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.manifest_path(name).is_file()
+    }
+
+    /// Names of all stored files, sorted
+    pub fn names(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(self.root.join(MANIFESTS_DIR))? {
+            let entry = entry?;
+            // Skip temp files and anything else that is not a manifest
+            if entry
+                .file_name()
+                .to_str()
+                .and_then(ChunkHash::from_hex)
+                .is_none()
+            {
+                continue;
+            }
+            let manifest: ChunkManifest = from_bincode(&fs::read(entry.path())?)?;
+            names.push(manifest.name);
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Read and verify a single chunk
+    pub fn read_chunk(&self, hash: &ChunkHash) -> io::Result<Vec<u8>> {
+        let envelope = fs::read(self.chunk_path(hash))?;
+        if EnvelopeHeader::parse(&envelope)?.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chunk {hash} is not an envelope"),
+            ));
+        }
+        let chunk = unwrap_auto(PayloadKind::Chunk, &envelope)?;
+        if ChunkHash::of(&chunk) != *hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chunk {hash} failed hash verification"),
+            ));
+        }
+        Ok(chunk)
+    }
+
+    /// Number of manifest references to a chunk
+    pub fn refcount(&self, hash: &ChunkHash) -> u64 {
+        self.refs.get(hash).copied().unwrap_or(0)
+    }
+
+    /// Remove the file stored under `name`
+    ///
+    /// Returns `false` if no such file exists. Chunk data is only deleted by [`ChunkStore::gc`].
+    pub fn remove(&mut self, name: &str) -> io::Result<bool> {
+        let manifest = match self.manifest(name) {
+            Ok(manifest) => manifest,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        fs::remove_file(self.manifest_path(name))?;
+        self.release(&manifest);
+        self.save_refs()?;
+        Ok(true)
+    }
+
+    /// Delete chunks that no stored file references
+    ///
+    /// Reference counts are recomputed from the manifests first, so the
+    /// collection is correct even if the reference table was stale.
+    pub fn gc(&mut self) -> io::Result<GcStats> {
+        self.refs = self.count_refs()?;
+        self.save_refs()?;
+
+        let mut stats = GcStats::default();
+        for fanout in fs::read_dir(self.root.join(CHUNKS_DIR))? {
+            let fanout = fanout?;
+            if !fanout.file_type()?.is_dir() {
+                continue;
+            }
+            let prefix = fanout.file_name().to_string_lossy().into_owned();
+            for entry in fs::read_dir(fanout.path())? {
+                let entry = entry?;
+                let hex = format!("{prefix}{}", entry.file_name().to_string_lossy());
+                let referenced =
+                    ChunkHash::from_hex(&hex).is_some_and(|hash| self.refs.contains_key(&hash));
+                // Leftover temp files from interrupted writes are collected too
+                if !referenced {
+                    stats.bytes_freed += entry.metadata()?.len();
+                    fs::remove_file(entry.path())?;
+                    stats.chunks_removed += 1;
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    fn release(&mut self, manifest: &ChunkManifest) {
+        for chunk in &manifest.chunks {
+            if let Some(count) = self.refs.get_mut(&chunk.hash) {
+                *count -= 1;
+                if *count == 0 {
+                    self.refs.remove(&chunk.hash);
+                }
+            }
+        }
+    }
+
+    fn count_refs(&self) -> io::Result<HashMap<ChunkHash, u64>> {
+        let mut refs = HashMap::new();
+        for name in self.names()? {
+            for chunk in self.manifest(&name)?.chunks {
+                *refs.entry(chunk.hash).or_insert(0) += 1;
+            }
+        }
+        Ok(refs)
+    }
+
+    fn save_refs(&self) -> io::Result<()> {
+        let mut table: Vec<(ChunkHash, u64)> = self.refs.iter().map(|(h, c)| (*h, *c)).collect();
+        table.sort();
+        write_atomic(self.root.join(REFS_FILE), &to_bincode(&table)?)
+    }
+
+    fn chunk_path(&self, hash: &ChunkHash) -> PathBuf {
+        let hex = hash.to_hex();
+        self.root.join(CHUNKS_DIR).join(&hex[..2]).join(&hex[2..])
+    }
+
+    fn manifest_path(&self, name: &str) -> PathBuf {
+        // Hashed so that names of any length map to a valid file name
+        self.root
+            .join(MANIFESTS_DIR)
+            .join(ChunkHash::of(name.as_bytes()).to_hex())
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.is_empty() {
+        return None;
+    }
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    fn small_chunks() -> ChunkStoreOptions {
+        ChunkStoreOptions {
+            cdc: CdcConfig::new(256, 1024, 4096).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hash_hex_roundtrip() {
+        let hash = ChunkHash::of(b"chunk");
+        assert_eq!(ChunkHash::from_hex(&hash.to_hex()), Some(hash));
+        assert_eq!(ChunkHash::from_hex("abc"), None);
+        assert_eq!(ChunkHash::from_hex(&"zz".repeat(32)), None);
+    }
+
+    #[test]
+    fn test_put_get_dedups_snapshots() {
+        let dir = tempdir().unwrap();
+        let mut store = ChunkStore::with_options(dir.path(), small_chunks()).unwrap();
+
+        let v1 = pseudo_random(100_000, 1);
+        let mut v2 = v1.clone();
+        v2[50_000..50_010].copy_from_slice(b"0123456789");
+
+        let first = store.put("snap/v1", Cursor::new(&v1)).unwrap();
+        assert_eq!(first.new_chunks, first.chunks);
+        let second = store.put("snap/v2", Cursor::new(&v2)).unwrap();
+        assert!(second.new_chunks <= 3, "{second:?}");
+        assert_eq!(second.size, v2.len() as u64);
+
+        let mut out = Vec::new();
+        store.get("snap/v1", &mut out).unwrap();
+        assert_eq!(out, v1);
+        out.clear();
+        store.get("snap/v2", &mut out).unwrap();
+        assert_eq!(out, v2);
+
+        assert_eq!(store.names().unwrap(), vec!["snap/v1", "snap/v2"]);
+        assert_eq!(store.manifest("snap/v1").unwrap().name, "snap/v1");
+        let shared = store.manifest("snap/v1").unwrap().chunks[0].hash;
+        assert_eq!(store.refcount(&shared), 2);
+    }
+
+    #[test]
+    fn test_remove_and_gc() {
+        let dir = tempdir().unwrap();
+        let mut store = ChunkStore::with_options(dir.path(), small_chunks()).unwrap();
+
+        let keep = pseudo_random(20_000, 2);
+        let removed = pseudo_random(20_000, 3);
+        store.put("keep", Cursor::new(&keep)).unwrap();
+        let dropped = store.put("drop", Cursor::new(&removed)).unwrap();
+
+        assert!(store.remove("drop").unwrap());
+        assert!(!store.remove("drop").unwrap());
+        assert!(!store.contains("drop"));
+
+        let stats = store.gc().unwrap();
+        assert_eq!(stats.chunks_removed, dropped.new_chunks);
+        assert_eq!(stats.bytes_freed, dropped.stored_bytes);
+
+        // Reopening rebuilds state from disk and the kept file is intact
+        fs::remove_file(dir.path().join(REFS_FILE)).unwrap();
+        let store = ChunkStore::with_options(dir.path(), small_chunks()).unwrap();
+        let mut out = Vec::new();
+        store.get("keep", &mut out).unwrap();
+        assert_eq!(out, keep);
+        let first = store.manifest("keep").unwrap().chunks[0].hash;
+        assert_eq!(store.refcount(&first), 1);
+    }
+
+    #[test]
+    fn test_long_names() {
+        let dir = tempdir().unwrap();
+        let mut store = ChunkStore::with_options(dir.path(), small_chunks()).unwrap();
+        let name = "datasets/".repeat(100) + "corpus.bin";
+        let data = pseudo_random(3_000, 6);
+
+        store.put(&name, Cursor::new(&data)).unwrap();
+        assert!(store.contains(&name));
+        assert_eq!(store.names().unwrap(), vec![name.clone()]);
+        let mut out = Vec::new();
+        store.get(&name, &mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn test_corrupt_chunk_detected() {
+        let dir = tempdir().unwrap();
+        let mut store = ChunkStore::with_options(dir.path(), small_chunks()).unwrap();
+        store
+            .put("file", Cursor::new(pseudo_random(5_000, 4)))
+            .unwrap();
+
+        let hash = store.manifest("file").unwrap().chunks[0].hash;
+        let path = store.chunk_path(&hash);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+
+        let err = store.get("file", &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_put_over_corrupt_manifest_fails() {
+        let dir = tempdir().unwrap();
+        let mut store = ChunkStore::with_options(dir.path(), small_chunks()).unwrap();
+        let data = pseudo_random(5_000, 5);
+        store.put("file", Cursor::new(&data)).unwrap();
+        let first = store.manifest("file").unwrap().chunks[0].hash;
+
+        // Overwriting must not silently drop the old manifest's references
+        fs::write(store.manifest_path("file"), b"garbage").unwrap();
+        assert!(store.put("file", Cursor::new(&data)).is_err());
+        assert_eq!(store.refcount(&first), 1);
+    }
+}
@@ -0,0 +1,149 @@
+//! Durability (fsync) policy for writers
+//!
+//! Flushing a writer only hands data to the OS; a power loss can still drop
+//! it. [`Durability`] selects how far a finished write is pushed towards
+//! stable storage, and [`Durable`] applies it to files and to the buffered
+//! writers wrapping them.
+//!
+//! The file helpers take the policy through
+//! [`FileWriteOptions::durability`](super::FileWriteOptions::durability);
+//! [`StreamWriter::finish_with`](super::StreamWriter::finish_with) and
+//! [`Durable::sync`] cover writers opened by the caller.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{buffered_writer, Durability, Durable};
+//! use std::io::Write;
+//!
+//! let mut writer = buffered_writer("engram.bin").unwrap();
+//! writer.write_all(b"payload").unwrap();
+//! // Flushes the buffer and waits until data and metadata are on disk
+//! writer.sync(Durability::Full).unwrap();
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// How far a finished write is synced to stable storage
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// Flush to the OS only; data may be lost on power failure
+    None,
+    /// Sync file contents (`sync_data`); metadata such as mtime may lag
+    Data,
+    /// Sync file contents and metadata (`sync_all`), and the directory after a rename
+    #[default]
+    Full,
+}
+
+impl Durability {
+    /// Sync `file` according to this policy
+    pub fn sync_file(self, file: &File) -> io::Result<()> {
+        match self {
+            Self::None => Ok(()),
+            Self::Data => file.sync_data(),
+            Self::Full => file.sync_all(),
+        }
+    }
+
+    /// Whether a rename must be followed by a directory sync
+    pub(crate) fn syncs_dir(self) -> bool {
+        self != Self::None
+    }
+}
+
+/// Writer that can flush its buffers and sync the underlying file
+pub trait Durable: Write {
+    /// Flush buffered data and sync it according to `durability`
+    fn sync(&mut self, durability: Durability) -> io::Result<()>;
+}
+
+impl Durable for File {
+    fn sync(&mut self, durability: Durability) -> io::Result<()> {
+        self.flush()?;
+        durability.sync_file(self)
+    }
+}
+
+impl<W: Durable> Durable for BufWriter<W> {
+    fn sync(&mut self, durability: Durability) -> io::Result<()> {
+        self.flush()?;
+        self.get_mut().sync(durability)
+    }
+}
+
+impl<W: Durable + ?Sized> Durable for &mut W {
+    fn sync(&mut self, durability: Durability) -> io::Result<()> {
+        (**self).sync(durability)
+    }
+}
+
+#[cfg(feature = "async")]
+pub mod async_durability {
+    //! Async durability support
+
+    use std::future::Future;
+    use std::io;
+
+    use tokio::fs::File;
+    use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+    use super::Durability;
+
+    impl Durability {
+        /// Sync `file` according to this policy (async)
+        pub async fn sync_file_async(self, file: &File) -> io::Result<()> {
+            match self {
+                Self::None => Ok(()),
+                Self::Data => file.sync_data().await,
+                Self::Full => file.sync_all().await,
+            }
+        }
+    }
+
+    /// Async writer that can flush its buffers and sync the underlying file
+    pub trait AsyncDurable: AsyncWrite + Unpin {
+        /// Flush buffered data and sync it according to `durability`
+        fn sync(&mut self, durability: Durability) -> impl Future<Output = io::Result<()>> + Send;
+    }
+
+    impl AsyncDurable for File {
+        async fn sync(&mut self, durability: Durability) -> io::Result<()> {
+            self.flush().await?;
+            durability.sync_file_async(self).await
+        }
+    }
+
+    impl<W: AsyncDurable + Send> AsyncDurable for BufWriter<W> {
+        async fn sync(&mut self, durability: Durability) -> io::Result<()> {
+            self.flush().await?;
+            self.get_mut().sync(durability).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_is_full() {
+        assert_eq!(Durability::default(), Durability::Full);
+        assert!(Durability::Data.syncs_dir());
+        assert!(!Durability::None.syncs_dir());
+    }
+
+    #[test]
+    fn test_buffered_sync_flushes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("synced.bin");
+
+        for durability in [Durability::None, Durability::Data, Durability::Full] {
+            let mut writer = BufWriter::new(File::create(&path).unwrap());
+            writer.write_all(b"buffered").unwrap();
+            writer.sync(durability).unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), b"buffered");
+        }
+    }
+}
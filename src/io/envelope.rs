@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::io;
 
 const MAGIC: [u8; 4] = *b"EDN1";
@@ -8,13 +9,55 @@ const HEADER_LEN: usize = 16;
 pub enum PayloadKind {
     EngramBincode = 1,
     SubEngramBincode = 2,
+    /// Raw chunk in a content-addressed chunk store
+    Chunk = 3,
+    /// Merkle tree over the blocks of a file
+    MerkleTree = 4,
+    /// MessagePack-encoded value
+    MessagePack = 5,
+    /// CBOR-encoded value
+    Cbor = 6,
+    /// postcard-encoded value
+    Postcard = 7,
 }
 
 impl PayloadKind {
-    fn from_u8(v: u8) -> Option<Self> {
+    pub(crate) fn from_u8(v: u8) -> Option<Self> {
         match v {
             1 => Some(Self::EngramBincode),
             2 => Some(Self::SubEngramBincode),
+            3 => Some(Self::Chunk),
+            4 => Some(Self::MerkleTree),
+            5 => Some(Self::MessagePack),
+            6 => Some(Self::Cbor),
+            7 => Some(Self::Postcard),
+            _ => None,
+        }
+    }
+
+    /// Short name used by tooling (e.g. `engram`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::EngramBincode => "engram",
+            Self::SubEngramBincode => "sub-engram",
+            Self::Chunk => "chunk",
+            Self::MerkleTree => "merkle-tree",
+            Self::MessagePack => "msgpack",
+            Self::Cbor => "cbor",
+            Self::Postcard => "postcard",
+        }
+    }
+
+    /// Look up a payload kind by its short name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "engram" => Some(Self::EngramBincode),
+            "sub-engram" | "subengram" => Some(Self::SubEngramBincode),
+            "chunk" => Some(Self::Chunk),
+            "merkle-tree" => Some(Self::MerkleTree),
+            "msgpack" | "messagepack" => Some(Self::MessagePack),
+            "cbor" => Some(Self::Cbor),
+            "postcard" => Some(Self::Postcard),
             _ => None,
         }
     }
@@ -29,7 +72,7 @@ pub enum CompressionCodec {
 }
 
 impl CompressionCodec {
-    fn from_u8(v: u8) -> Option<Self> {
+    pub(crate) fn from_u8(v: u8) -> Option<Self> {
         match v {
             0 => Some(Self::None),
             1 => Some(Self::Zstd),
@@ -37,6 +80,34 @@ impl CompressionCodec {
             _ => None,
         }
     }
+
+    /// Short name used by tooling (e.g. `zstd`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    /// Whether support for this codec is compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::None => true,
+            Self::Zstd => cfg!(feature = "compression-zstd"),
+            Self::Lz4 => cfg!(feature = "compression-lz4"),
+        }
+    }
+
+    /// Look up a codec by its short name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "zstd" => Some(Self::Zstd),
+            "lz4" => Some(Self::Lz4),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -54,6 +125,48 @@ impl Default for BinaryWriteOptions {
     }
 }
 
+/// Fields decoded from the fixed-size envelope header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnvelopeHeader {
+    /// Kind of payload stored in the envelope
+    pub kind: PayloadKind,
+    /// Codec used to compress the payload
+    pub codec: CompressionCodec,
+    /// Reserved header field (currently always zero)
+    pub reserved: u16,
+    /// Length of the payload after decompression
+    pub uncompressed_len: u64,
+}
+
+impl EnvelopeHeader {
+    /// Size of the encoded header in bytes
+    pub const LEN: usize = HEADER_LEN;
+
+    /// Parse the header at the start of `data`
+    ///
+    /// Returns `Ok(None)` for legacy data that does not start with an envelope header.
+    pub fn parse(data: &[u8]) -> io::Result<Option<Self>> {
+        if data.len() < HEADER_LEN || data[..4] != MAGIC {
+            return Ok(None);
+        }
+
+        let kind = PayloadKind::from_u8(data[4])
+            .ok_or_else(|| io::Error::other("unknown envelope payload kind"))?;
+        let codec = CompressionCodec::from_u8(data[5])
+            .ok_or_else(|| io::Error::other("unknown envelope compression codec"))?;
+        let reserved = u16::from_le_bytes([data[6], data[7]]);
+        let uncompressed_len =
+            u64::from_le_bytes(data[8..16].try_into().expect("slice length checked"));
+
+        Ok(Some(Self {
+            kind,
+            codec,
+            reserved,
+            uncompressed_len,
+        }))
+    }
+}
+
 pub fn wrap_or_legacy(
     kind: PayloadKind,
     opts: BinaryWriteOptions,
@@ -62,7 +175,14 @@ pub fn wrap_or_legacy(
     if opts.codec == CompressionCodec::None {
         return Ok(raw.to_vec());
     }
+    wrap(kind, opts, raw)
+}
 
+/// Wrap `raw` in an envelope, writing a header even for `CompressionCodec::None`
+///
+/// Unlike [`wrap_or_legacy`], the result can always be told apart from raw
+/// data that happens to start with the envelope magic.
+pub fn wrap(kind: PayloadKind, opts: BinaryWriteOptions, raw: &[u8]) -> io::Result<Vec<u8>> {
     let compressed = compress(opts.codec, raw, opts.level)?;
 
     let mut out = Vec::with_capacity(HEADER_LEN + compressed.len());
@@ -77,34 +197,76 @@ pub fn wrap_or_legacy(
 }
 
 pub fn unwrap_auto(expected_kind: PayloadKind, data: &[u8]) -> io::Result<Vec<u8>> {
-    if data.len() < HEADER_LEN || data[..4] != MAGIC {
-        return Ok(data.to_vec());
-    }
+    unwrap_cow(expected_kind, data).map(Cow::into_owned)
+}
+
+/// Like [`unwrap_auto`], but borrows from `data` when no decompression is needed
+pub fn unwrap_cow(expected_kind: PayloadKind, data: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    let Some(header) = EnvelopeHeader::parse(data)? else {
+        return Ok(Cow::Borrowed(data));
+    };
 
-    let kind = PayloadKind::from_u8(data[4])
-        .ok_or_else(|| io::Error::other("unknown envelope payload kind"))?;
-    if kind != expected_kind {
+    if header.kind != expected_kind {
         return Err(io::Error::other("unexpected envelope payload kind"));
     }
 
-    let codec = CompressionCodec::from_u8(data[5])
-        .ok_or_else(|| io::Error::other("unknown envelope compression codec"))?;
-    let uncompressed_len =
-        u64::from_le_bytes(data[8..16].try_into().expect("slice length checked")) as usize;
-
     let payload = &data[HEADER_LEN..];
-    let decoded = match codec {
-        CompressionCodec::None => payload.to_vec(),
-        CompressionCodec::Zstd | CompressionCodec::Lz4 => decompress(codec, payload)?,
+    let decoded = match header.codec {
+        CompressionCodec::None => Cow::Borrowed(payload),
+        CompressionCodec::Zstd | CompressionCodec::Lz4 => {
+            Cow::Owned(decompress(header.codec, payload)?)
+        }
     };
 
-    if decoded.len() != uncompressed_len {
+    if decoded.len() as u64 != header.uncompressed_len {
         return Err(io::Error::other("envelope size mismatch"));
     }
 
     Ok(decoded)
 }
 
+/// Like [`unwrap_auto`], but always borrows from `data`
+///
+/// Legacy data and uncompressed envelopes yield a slice of `data`, so values
+/// deserialized from it with e.g. [`from_bincode_borrowed`](super::from_bincode_borrowed)
+/// can borrow all the way back to the input buffer or memory mapping.
+/// Compressed envelopes are rejected with `InvalidInput` before decompressing.
+pub fn unwrap_borrowed(expected_kind: PayloadKind, data: &[u8]) -> io::Result<&[u8]> {
+    if let Some(header) = EnvelopeHeader::parse(data)? {
+        if header.codec != CompressionCodec::None {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "envelope payload is {}-compressed and cannot be borrowed",
+                    header.codec.name()
+                ),
+            ));
+        }
+    }
+    match unwrap_cow(expected_kind, data)? {
+        Cow::Borrowed(payload) => Ok(payload),
+        Cow::Owned(_) => Err(io::Error::other("envelope payload cannot be borrowed")),
+    }
+}
+
+/// Re-encode envelope data with new write options
+///
+/// Envelopes keep the payload kind recorded in their header; legacy (headerless)
+/// data is wrapped as `legacy_kind`. Re-encoding to `CompressionCodec::None`
+/// produces legacy raw bytes, matching [`wrap_or_legacy`].
+pub fn rewrap(
+    data: &[u8],
+    new_opts: BinaryWriteOptions,
+    legacy_kind: PayloadKind,
+) -> io::Result<Vec<u8>> {
+    let kind = match EnvelopeHeader::parse(data)? {
+        Some(header) => header.kind,
+        None => legacy_kind,
+    };
+    let raw = unwrap_auto(kind, data)?;
+    wrap_or_legacy(kind, new_opts, &raw)
+}
+
 fn compress(codec: CompressionCodec, raw: &[u8], level: Option<i32>) -> io::Result<Vec<u8>> {
     match codec {
         CompressionCodec::None => Ok(raw.to_vec()),
@@ -179,3 +341,60 @@ fn decompress_lz4(_payload: &[u8]) -> io::Result<Vec<u8>> {
         ))
     }
 }
+
+#[cfg(feature = "async")]
+pub mod async_envelope {
+    //! Async envelope reads and writes over tokio streams
+    //!
+    //! Envelopes written here always carry a header (see [`wrap`](super::wrap)).
+    //! The `_prefixed` variants add a `u32` length prefix so several envelopes
+    //! can follow each other on one stream.
+
+    use std::io;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::super::serialize::async_serialize::{read_length_prefixed, write_length_prefixed};
+    use super::{unwrap_auto, wrap, BinaryWriteOptions, PayloadKind};
+
+    /// Wrap `raw` in an envelope and write it to `writer`
+    pub async fn write_envelope<W: AsyncWriteExt + Unpin>(
+        writer: &mut W,
+        kind: PayloadKind,
+        opts: BinaryWriteOptions,
+        raw: &[u8],
+    ) -> io::Result<()> {
+        writer.write_all(&wrap(kind, opts, raw)?).await
+    }
+
+    /// Read `reader` to its end and unwrap the envelope (or legacy data) it holds
+    pub async fn read_envelope<R: AsyncReadExt + Unpin>(
+        reader: &mut R,
+        expected_kind: PayloadKind,
+    ) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        unwrap_auto(expected_kind, &data)
+    }
+
+    /// Write a length-prefixed envelope
+    pub async fn write_envelope_prefixed<W: AsyncWriteExt + Unpin>(
+        writer: &mut W,
+        kind: PayloadKind,
+        opts: BinaryWriteOptions,
+        raw: &[u8],
+    ) -> io::Result<()> {
+        write_length_prefixed(writer, &wrap(kind, opts, raw)?).await
+    }
+
+    /// Read and unwrap a length-prefixed envelope; `Ok(None)` at end of stream
+    pub async fn read_envelope_prefixed<R: AsyncReadExt + Unpin>(
+        reader: &mut R,
+        expected_kind: PayloadKind,
+    ) -> io::Result<Option<Vec<u8>>> {
+        match read_length_prefixed(reader).await? {
+            Some(data) => unwrap_auto(expected_kind, &data).map(Some),
+            None => Ok(None),
+        }
+    }
+}
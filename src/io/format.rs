@@ -0,0 +1,549 @@
+//! Format-agnostic serialization
+//!
+//! [`SerializationFormat`] puts the serialization helpers behind one trait, so
+//! the format becomes a type parameter or a runtime [`Format`] value instead
+//! of a choice between `to_bincode` and `to_json` at every call site.
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{Bincode, Format, Json, SerializationFormat};
+//!
+//! fn roundtrip<F: SerializationFormat>(format: F) -> Vec<u32> {
+//!     let bytes = format.encode(&vec![1u32, 2, 3]).unwrap();
+//!     format.decode(&bytes).unwrap()
+//! }
+//!
+//! assert_eq!(roundtrip(Bincode), vec![1, 2, 3]);
+//! assert_eq!(roundtrip(Json), vec![1, 2, 3]);
+//!
+//! let format = Format::from_name("json").unwrap();
+//! assert_eq!(format.encode(&[1u8, 2]).unwrap(), b"[1,2]");
+//! ```
+
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::atomic::{FileWriteOptions, OutputFile};
+use super::bincode_config::BincodeConfig;
+use super::buffer::DEFAULT_BUFFER_SIZE;
+use super::envelope::{unwrap_auto, wrap_or_legacy, BinaryWriteOptions, PayloadKind};
+
+/// Serialization format usable in place of the `to_*`/`from_*` helpers
+///
+/// Only [`encode`](Self::encode) and [`decode`](Self::decode) are required;
+/// formats that can stream override the reader/writer methods.
+pub trait SerializationFormat {
+    /// Short name of the format (e.g. `bincode`)
+    fn name(&self) -> &'static str;
+
+    /// Envelope payload kind reserved for this format, if it has one
+    ///
+    /// Bincode and JSON data can be wrapped as any kind; formats with their
+    /// own kind (MessagePack, CBOR, postcard) are only wrapped as that kind.
+    fn payload_kind(&self) -> Option<PayloadKind> {
+        None
+    }
+
+    /// Serialize `value` to bytes
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>>;
+
+    /// Deserialize a value from `bytes`
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T>;
+
+    /// Serialize `value` to a writer
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        writer.write_all(&self.encode(value)?)
+    }
+
+    /// Deserialize a value from a reader, reading it to the end
+    fn read_from<R: Read, T: serde::de::DeserializeOwned>(&self, reader: &mut R) -> io::Result<T> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.decode(&bytes)
+    }
+
+    /// Write `value` to a file
+    fn write_file<P: AsRef<Path>, T: serde::Serialize>(
+        &self,
+        path: P,
+        value: &T,
+    ) -> io::Result<()> {
+        self.write_file_with_options(path, value, FileWriteOptions::default())
+    }
+
+    /// Write `value` to a file with explicit file options
+    fn write_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+        &self,
+        path: P,
+        value: &T,
+        opts: FileWriteOptions,
+    ) -> io::Result<()> {
+        let file = OutputFile::create(path, opts)?;
+        let mut writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, file);
+        self.write_to(&mut writer, value)?;
+        writer.into_inner().map_err(|e| e.into_error())?.commit()
+    }
+
+    /// Read a value from a file
+    fn read_file<P: AsRef<Path>, T: serde::de::DeserializeOwned>(&self, path: P) -> io::Result<T> {
+        self.decode(&std::fs::read(path)?)
+    }
+
+    /// Serialize `value` and wrap it with [`wrap_or_legacy`]
+    ///
+    /// Fails with `InvalidInput` if `kind` is not the format's
+    /// [`payload_kind`](Self::payload_kind).
+    fn wrap_envelope<T: serde::Serialize>(
+        &self,
+        kind: PayloadKind,
+        opts: BinaryWriteOptions,
+        value: &T,
+    ) -> io::Result<Vec<u8>> {
+        check_payload_kind(self.name(), self.payload_kind(), kind)?;
+        wrap_or_legacy(kind, opts, &self.encode(value)?)
+    }
+
+    /// Unwrap `data` with [`unwrap_auto`] and deserialize the payload
+    ///
+    /// Fails with `InvalidInput` if `expected_kind` is not the format's
+    /// [`payload_kind`](Self::payload_kind).
+    fn unwrap_envelope<T: serde::de::DeserializeOwned>(
+        &self,
+        expected_kind: PayloadKind,
+        data: &[u8],
+    ) -> io::Result<T> {
+        check_payload_kind(self.name(), self.payload_kind(), expected_kind)?;
+        self.decode(&unwrap_auto(expected_kind, data)?)
+    }
+}
+
+fn check_payload_kind(name: &str, own: Option<PayloadKind>, kind: PayloadKind) -> io::Result<()> {
+    match own {
+        Some(own) if own != kind => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{name} data uses payload kind {}, not {}",
+                own.name(),
+                kind.name()
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Bincode format, as [`to_bincode`](super::to_bincode)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bincode;
+
+impl SerializationFormat for Bincode {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        super::to_bincode(value)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        super::from_bincode(bytes)
+    }
+}
+
+/// Bincode with explicit options, as [`to_bincode_with_config`](super::to_bincode_with_config)
+impl SerializationFormat for BincodeConfig {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        super::to_bincode_with_config(value, *self)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        super::from_bincode_with_config(bytes, *self)
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        super::write_bincode_with_config(writer, value, *self)
+    }
+
+    fn read_from<R: Read, T: serde::de::DeserializeOwned>(&self, reader: &mut R) -> io::Result<T> {
+        super::read_bincode_with_config(reader, *self)
+    }
+}
+
+/// Compact JSON format, as [`to_json`](super::to_json)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Json;
+
+impl SerializationFormat for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(io::Error::other)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        serde_json::from_slice(bytes).map_err(io::Error::other)
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        super::write_json_compact(writer, value)
+    }
+
+    fn read_from<R: Read, T: serde::de::DeserializeOwned>(&self, reader: &mut R) -> io::Result<T> {
+        super::read_json(reader)
+    }
+}
+
+/// Pretty-printed JSON format, as [`to_json_pretty`](super::to_json_pretty)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JsonPretty;
+
+impl SerializationFormat for JsonPretty {
+    fn name(&self) -> &'static str {
+        "json-pretty"
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(value).map_err(io::Error::other)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        Json.decode(bytes)
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        super::write_json_pretty(writer, value)
+    }
+
+    fn read_from<R: Read, T: serde::de::DeserializeOwned>(&self, reader: &mut R) -> io::Result<T> {
+        super::read_json(reader)
+    }
+}
+
+/// MessagePack format, as [`to_msgpack`](super::to_msgpack)
+#[cfg(feature = "format-msgpack")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MessagePack;
+
+#[cfg(feature = "format-msgpack")]
+impl SerializationFormat for MessagePack {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn payload_kind(&self) -> Option<PayloadKind> {
+        Some(PayloadKind::MessagePack)
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        super::to_msgpack(value)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        super::from_msgpack(bytes)
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        super::write_msgpack(writer, value)
+    }
+}
+
+/// CBOR format, as [`to_cbor`](super::to_cbor)
+#[cfg(feature = "format-cbor")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cbor;
+
+#[cfg(feature = "format-cbor")]
+impl SerializationFormat for Cbor {
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn payload_kind(&self) -> Option<PayloadKind> {
+        Some(PayloadKind::Cbor)
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        super::to_cbor(value)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        super::from_cbor(bytes)
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        super::write_cbor(writer, value)
+    }
+}
+
+/// postcard format, as [`to_postcard`](super::to_postcard)
+#[cfg(feature = "format-postcard")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Postcard;
+
+#[cfg(feature = "format-postcard")]
+impl SerializationFormat for Postcard {
+    fn name(&self) -> &'static str {
+        "postcard"
+    }
+
+    fn payload_kind(&self) -> Option<PayloadKind> {
+        Some(PayloadKind::Postcard)
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        super::to_postcard(value)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        super::from_postcard(bytes)
+    }
+}
+
+/// Serialization format chosen at runtime
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// See [`Bincode`]
+    #[default]
+    Bincode,
+    /// See [`Json`]
+    Json,
+    /// See [`JsonPretty`]
+    JsonPretty,
+    /// See [`MessagePack`]
+    #[cfg(feature = "format-msgpack")]
+    MessagePack,
+    /// See [`Cbor`]
+    #[cfg(feature = "format-cbor")]
+    Cbor,
+    /// See [`Postcard`]
+    #[cfg(feature = "format-postcard")]
+    Postcard,
+}
+
+/// Dispatch a trait method call to the format struct behind a [`Format`]
+macro_rules! dispatch {
+    ($format:expr, $f:ident => $call:expr) => {
+        match $format {
+            Format::Bincode => {
+                let $f = Bincode;
+                $call
+            }
+            Format::Json => {
+                let $f = Json;
+                $call
+            }
+            Format::JsonPretty => {
+                let $f = JsonPretty;
+                $call
+            }
+            #[cfg(feature = "format-msgpack")]
+            Format::MessagePack => {
+                let $f = MessagePack;
+                $call
+            }
+            #[cfg(feature = "format-cbor")]
+            Format::Cbor => {
+                let $f = Cbor;
+                $call
+            }
+            #[cfg(feature = "format-postcard")]
+            Format::Postcard => {
+                let $f = Postcard;
+                $call
+            }
+        }
+    };
+}
+
+impl Format {
+    /// Look up a format by its short name (case-insensitive)
+    ///
+    /// Returns `None` for unknown names and for formats whose feature is disabled.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bincode" => Some(Self::Bincode),
+            "json" => Some(Self::Json),
+            "json-pretty" => Some(Self::JsonPretty),
+            #[cfg(feature = "format-msgpack")]
+            "msgpack" | "messagepack" => Some(Self::MessagePack),
+            #[cfg(feature = "format-cbor")]
+            "cbor" => Some(Self::Cbor),
+            #[cfg(feature = "format-postcard")]
+            "postcard" => Some(Self::Postcard),
+            _ => None,
+        }
+    }
+}
+
+impl SerializationFormat for Format {
+    fn name(&self) -> &'static str {
+        dispatch!(self, f => f.name())
+    }
+
+    fn payload_kind(&self) -> Option<PayloadKind> {
+        dispatch!(self, f => f.payload_kind())
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        dispatch!(self, f => f.encode(value))
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        dispatch!(self, f => f.decode(bytes))
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        dispatch!(self, f => f.write_to(writer, value))
+    }
+
+    fn read_from<R: Read, T: serde::de::DeserializeOwned>(&self, reader: &mut R) -> io::Result<T> {
+        dispatch!(self, f => f.read_from(reader))
+    }
+}
+
+impl<F: SerializationFormat + ?Sized> SerializationFormat for &F {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn payload_kind(&self) -> Option<PayloadKind> {
+        (**self).payload_kind()
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        (**self).encode(value)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, bytes: &[u8]) -> io::Result<T> {
+        (**self).decode(bytes)
+    }
+
+    fn write_to<W: Write, T: serde::Serialize>(&self, writer: &mut W, value: &T) -> io::Result<()> {
+        (**self).write_to(writer, value)
+    }
+
+    fn read_from<R: Read, T: serde::de::DeserializeOwned>(&self, reader: &mut R) -> io::Result<T> {
+        (**self).read_from(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::CompressionCodec;
+    use serde::{Deserialize, Serialize};
+    use tempfile::tempdir;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        id: u64,
+        tags: Vec<String>,
+    }
+
+    fn sample() -> Record {
+        Record {
+            id: 7,
+            tags: vec!["a".into(), "b".into()],
+        }
+    }
+
+    fn all_formats() -> Vec<Format> {
+        [
+            "bincode",
+            "json",
+            "json-pretty",
+            "msgpack",
+            "cbor",
+            "postcard",
+        ]
+        .into_iter()
+        .filter_map(Format::from_name)
+        .collect()
+    }
+
+    #[test]
+    fn test_runtime_format_matches_helpers() {
+        assert_eq!(
+            Format::Bincode.encode(&sample()).unwrap(),
+            crate::io::to_bincode(&sample()).unwrap()
+        );
+        assert_eq!(
+            Format::Json.encode(&sample()).unwrap(),
+            crate::io::to_json(&sample()).unwrap().into_bytes()
+        );
+        for format in all_formats() {
+            assert_eq!(Format::from_name(format.name()), Some(format));
+            let bytes = format.encode(&sample()).unwrap();
+            assert_eq!(format.decode::<Record>(&bytes).unwrap(), sample());
+
+            let mut stream = Vec::new();
+            format.write_to(&mut stream, &sample()).unwrap();
+            assert_eq!(stream, bytes, "{}", format.name());
+            assert_eq!(
+                format.read_from::<_, Record>(&mut &stream[..]).unwrap(),
+                sample()
+            );
+        }
+    }
+
+    #[test]
+    fn test_files_and_envelopes() {
+        let dir = tempdir().unwrap();
+        let opts = BinaryWriteOptions {
+            codec: CompressionCodec::None,
+            level: None,
+        };
+        for format in all_formats() {
+            let path = dir.path().join(format.name());
+            format.write_file(&path, &sample()).unwrap();
+            assert_eq!(format.read_file::<_, Record>(&path).unwrap(), sample());
+
+            let kind = format
+                .payload_kind()
+                .unwrap_or(PayloadKind::SubEngramBincode);
+            let wrapped = format.wrap_envelope(kind, opts, &sample()).unwrap();
+            let unwrapped: Record = format.unwrap_envelope(kind, &wrapped).unwrap();
+            assert_eq!(unwrapped, sample());
+
+            if format.payload_kind().is_some() {
+                let err = format
+                    .wrap_envelope(PayloadKind::SubEngramBincode, opts, &sample())
+                    .unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+                let err = format
+                    .unwrap_envelope::<Record>(PayloadKind::SubEngramBincode, &wrapped)
+                    .unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_format_helpers() {
+        use crate::io::async_serialize::{read_format_prefixed, write_format_prefixed};
+
+        let mut wire = Vec::new();
+        for format in all_formats() {
+            write_format_prefixed(format, &mut wire, &sample())
+                .await
+                .unwrap();
+        }
+        let mut reader = &wire[..];
+        for format in all_formats() {
+            let record: Option<Record> = read_format_prefixed(&format, &mut reader).await.unwrap();
+            assert_eq!(record, Some(sample()));
+        }
+        assert!(
+            read_format_prefixed::<_, _, Record>(Format::Json, &mut reader)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,693 @@
+//! Length-prefixed framed record streams
+//!
+//! Raw bincode on a stream has no record boundaries, so one bad record makes
+//! the rest unreadable. [`FramedWriter`] writes each record after a length
+//! prefix and, optionally, a CRC-32 of the stored bytes; records may also be
+//! wrapped in an envelope with their own compression. [`FramedReader`] reads
+//! them back one at a time or as an iterator of typed records.
+//!
+//! Stream layout:
+//! ```text
+//! header:  "EDF1" | flags u8 | kind u8 | reserved u16
+//! record:  len u32 LE | crc32 u32 LE (if checksummed) | payload (len bytes)
+//! ```
+//! `flags` bit 0 marks checksummed records and bit 1 enveloped records, whose
+//! payload kind is `kind`. A record that fails its checksum or does not
+//! decode is reported as an error and skipped; reading continues with the
+//! next record. Truncation and I/O errors end the stream.
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{FramedReader, FramedWriter};
+//!
+//! let mut writer = FramedWriter::new(Vec::new()).unwrap();
+//! writer.write(&"first").unwrap();
+//! writer.write(&"second").unwrap();
+//! let wire = writer.finish().unwrap();
+//!
+//! let reader = FramedReader::new(&wire[..]).unwrap();
+//! let records: Vec<String> = reader.records().collect::<std::io::Result<_>>().unwrap();
+//! assert_eq!(records, vec!["first", "second"]);
+//! ```
+
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use super::envelope::{unwrap_auto, wrap, BinaryWriteOptions, PayloadKind};
+
+const MAGIC: [u8; 4] = *b"EDF1";
+const STREAM_HEADER_LEN: usize = 8;
+const FLAG_CHECKSUM: u8 = 1;
+const FLAG_ENVELOPE: u8 = 2;
+
+/// CRC-32 (IEEE) lookup table
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 (IEEE) of `data`
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        CRC_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// How records are framed
+#[derive(Clone, Copy, Debug)]
+pub struct FrameOptions {
+    /// Store a CRC-32 of each record (default: true)
+    pub checksum: bool,
+    /// Wrap each record in an envelope written with these options (default: none)
+    pub envelope: Option<BinaryWriteOptions>,
+    /// Payload kind recorded for enveloped records (default: `SubEngramBincode`)
+    pub kind: PayloadKind,
+}
+
+impl Default for FrameOptions {
+    fn default() -> Self {
+        Self {
+            checksum: true,
+            envelope: None,
+            kind: PayloadKind::SubEngramBincode,
+        }
+    }
+}
+
+/// Framing read back from a stream header
+#[derive(Clone, Copy, Debug)]
+struct FrameFormat {
+    checksum: bool,
+    envelope_kind: Option<PayloadKind>,
+}
+
+impl FrameFormat {
+    fn prefix_len(self) -> usize {
+        if self.checksum {
+            8
+        } else {
+            4
+        }
+    }
+}
+
+fn encode_stream_header(opts: &FrameOptions) -> [u8; STREAM_HEADER_LEN] {
+    let mut flags = 0;
+    if opts.checksum {
+        flags |= FLAG_CHECKSUM;
+    }
+    let mut kind = 0;
+    if opts.envelope.is_some() {
+        flags |= FLAG_ENVELOPE;
+        kind = opts.kind as u8;
+    }
+    [MAGIC[0], MAGIC[1], MAGIC[2], MAGIC[3], flags, kind, 0, 0]
+}
+
+fn decode_stream_header(header: &[u8; STREAM_HEADER_LEN]) -> io::Result<FrameFormat> {
+    if header[..4] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a framed record stream",
+        ));
+    }
+    let flags = header[4];
+    if flags & !(FLAG_CHECKSUM | FLAG_ENVELOPE) != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unknown framed stream flags",
+        ));
+    }
+    let envelope_kind = if flags & FLAG_ENVELOPE != 0 {
+        Some(PayloadKind::from_u8(header[5]).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "unknown envelope payload kind")
+        })?)
+    } else {
+        None
+    };
+    Ok(FrameFormat {
+        checksum: flags & FLAG_CHECKSUM != 0,
+        envelope_kind,
+    })
+}
+
+/// Encode one record, prefix included
+fn encode_record(opts: &FrameOptions, record: &[u8]) -> io::Result<Vec<u8>> {
+    let wrapped;
+    let payload = match opts.envelope {
+        Some(envelope) => {
+            wrapped = wrap(opts.kind, envelope, record)?;
+            &wrapped[..]
+        }
+        None => record,
+    };
+    let len = u32::try_from(payload.len()).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "record exceeds u32::MAX bytes")
+    })?;
+
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    if opts.checksum {
+        frame.extend_from_slice(&crc32(payload).to_le_bytes());
+    }
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+/// Split a record prefix into (payload length, expected checksum)
+fn decode_prefix(format: FrameFormat, prefix: &[u8; 8]) -> (u64, Option<u32>) {
+    let len = u32::from_le_bytes(prefix[..4].try_into().expect("4-byte slice")) as u64;
+    let crc = format
+        .checksum
+        .then(|| u32::from_le_bytes(prefix[4..8].try_into().expect("4-byte slice")));
+    (len, crc)
+}
+
+/// Record as read from the stream, before verification
+struct RawFrame {
+    index: u64,
+    crc: Option<u32>,
+    payload: Vec<u8>,
+}
+
+impl RawFrame {
+    /// Verify the checksum and unwrap the envelope, if any
+    fn decode(self, format: FrameFormat) -> io::Result<Vec<u8>> {
+        if let Some(expected) = self.crc {
+            if crc32(&self.payload) != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record {}: checksum mismatch", self.index),
+                ));
+            }
+        }
+        match format.envelope_kind {
+            Some(kind) => unwrap_auto(kind, &self.payload),
+            None => Ok(self.payload),
+        }
+    }
+}
+
+fn truncated(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, format!("truncated {what}"))
+}
+
+/// Writer that frames each record with a length prefix and optional checksum
+pub struct FramedWriter<W: Write> {
+    writer: W,
+    opts: FrameOptions,
+    records: u64,
+}
+
+impl<W: Write> FramedWriter<W> {
+    /// Start a framed stream with default options (checksummed, no envelope)
+    pub fn new(writer: W) -> io::Result<Self> {
+        Self::with_options(writer, FrameOptions::default())
+    }
+
+    /// Start a framed stream, writing its header to `writer`
+    pub fn with_options(mut writer: W, opts: FrameOptions) -> io::Result<Self> {
+        writer.write_all(&encode_stream_header(&opts))?;
+        Ok(Self {
+            writer,
+            opts,
+            records: 0,
+        })
+    }
+
+    /// Write one raw record
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        self.writer.write_all(&encode_record(&self.opts, record)?)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Serialize `value` with bincode and write it as one record
+    pub fn write<T: serde::Serialize>(&mut self, value: &T) -> io::Result<()> {
+        self.write_record(&super::to_bincode(value)?)
+    }
+
+    /// Number of records written so far
+    pub fn records_written(&self) -> u64 {
+        self.records
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reader for streams written by [`FramedWriter`]
+pub struct FramedReader<R: Read> {
+    reader: R,
+    format: FrameFormat,
+    records: u64,
+}
+
+impl<R: Read> FramedReader<R> {
+    /// Read the stream header from `reader`
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; STREAM_HEADER_LEN];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => truncated("framed stream header"),
+            _ => e,
+        })?;
+        Ok(Self {
+            reader,
+            format: decode_stream_header(&header)?,
+            records: 0,
+        })
+    }
+
+    /// Whether records carry a checksum
+    pub fn has_checksum(&self) -> bool {
+        self.format.checksum
+    }
+
+    /// Payload kind of enveloped records, if records are enveloped
+    pub fn envelope_kind(&self) -> Option<PayloadKind> {
+        self.format.envelope_kind
+    }
+
+    /// Read the next raw record; `Ok(None)` at end of stream
+    ///
+    /// A checksum or envelope error affects only this record; the next call
+    /// reads the following one.
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.read_frame()? {
+            Some(frame) => frame.decode(self.format).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read and deserialize the next bincode record; `Ok(None)` at end of stream
+    pub fn read<T: serde::de::DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+        match self.read_record()? {
+            Some(bytes) => super::from_bincode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterate over the remaining records, deserialized from bincode
+    pub fn records<T: serde::de::DeserializeOwned>(self) -> FramedRecords<R, T> {
+        FramedRecords {
+            reader: self,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Number of records read so far, including bad ones
+    pub fn records_read(&self) -> u64 {
+        self.records
+    }
+
+    /// Return the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read one frame without verifying it
+    fn read_frame(&mut self) -> io::Result<Option<RawFrame>> {
+        let prefix_len = self.format.prefix_len();
+        let mut prefix = [0u8; 8];
+        let mut filled = 0;
+        while filled < prefix_len {
+            let n = self.reader.read(&mut prefix[filled..prefix_len])?;
+            if n == 0 {
+                if filled == 0 {
+                    return Ok(None);
+                }
+                return Err(truncated("record prefix"));
+            }
+            filled += n;
+        }
+
+        let (len, crc) = decode_prefix(self.format, &prefix);
+        let mut payload = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(truncated("record"));
+        }
+        let index = self.records;
+        self.records += 1;
+        Ok(Some(RawFrame {
+            index,
+            crc,
+            payload,
+        }))
+    }
+}
+
+/// Iterator of typed records from a [`FramedReader`]
+///
+/// Bad records yield an error and iteration continues; truncation or an I/O
+/// error yields an error and ends iteration.
+pub struct FramedRecords<R: Read, T> {
+    reader: FramedReader<R>,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T: serde::de::DeserializeOwned> Iterator for FramedRecords<R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.read_frame() {
+            Ok(Some(frame)) => Some(
+                frame
+                    .decode(self.reader.format)
+                    .and_then(|bytes| super::from_bincode(&bytes)),
+            ),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub mod async_framed {
+    //! Async framed record streams
+
+    use std::io;
+
+    use futures::stream::{self, Stream};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    use super::{
+        decode_prefix, decode_stream_header, encode_record, encode_stream_header, truncated,
+        FrameFormat, FrameOptions, RawFrame, STREAM_HEADER_LEN,
+    };
+    use crate::io::envelope::PayloadKind;
+
+    /// Async counterpart of [`FramedWriter`](super::FramedWriter)
+    pub struct AsyncFramedWriter<W: AsyncWrite + Unpin> {
+        writer: W,
+        opts: FrameOptions,
+        records: u64,
+    }
+
+    impl<W: AsyncWrite + Unpin> AsyncFramedWriter<W> {
+        /// Start a framed stream with default options (checksummed, no envelope)
+        pub async fn new(writer: W) -> io::Result<Self> {
+            Self::with_options(writer, FrameOptions::default()).await
+        }
+
+        /// Start a framed stream, writing its header to `writer`
+        pub async fn with_options(mut writer: W, opts: FrameOptions) -> io::Result<Self> {
+            writer.write_all(&encode_stream_header(&opts)).await?;
+            Ok(Self {
+                writer,
+                opts,
+                records: 0,
+            })
+        }
+
+        /// Write one raw record
+        pub async fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+            let frame = encode_record(&self.opts, record)?;
+            self.writer.write_all(&frame).await?;
+            self.records += 1;
+            Ok(())
+        }
+
+        /// Serialize `value` with bincode and write it as one record
+        pub async fn write<T: serde::Serialize>(&mut self, value: &T) -> io::Result<()> {
+            self.write_record(&crate::io::to_bincode(value)?).await
+        }
+
+        /// Number of records written so far
+        pub fn records_written(&self) -> u64 {
+            self.records
+        }
+
+        /// Flush the underlying writer
+        pub async fn flush(&mut self) -> io::Result<()> {
+            self.writer.flush().await
+        }
+
+        /// Flush and return the underlying writer
+        pub async fn finish(mut self) -> io::Result<W> {
+            self.writer.flush().await?;
+            Ok(self.writer)
+        }
+    }
+
+    /// Async counterpart of [`FramedReader`](super::FramedReader)
+    pub struct AsyncFramedReader<R: AsyncRead + Unpin> {
+        reader: R,
+        format: FrameFormat,
+        records: u64,
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncFramedReader<R> {
+        /// Read the stream header from `reader`
+        pub async fn new(mut reader: R) -> io::Result<Self> {
+            let mut header = [0u8; STREAM_HEADER_LEN];
+            reader
+                .read_exact(&mut header)
+                .await
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::UnexpectedEof => truncated("framed stream header"),
+                    _ => e,
+                })?;
+            Ok(Self {
+                reader,
+                format: decode_stream_header(&header)?,
+                records: 0,
+            })
+        }
+
+        /// Whether records carry a checksum
+        pub fn has_checksum(&self) -> bool {
+            self.format.checksum
+        }
+
+        /// Payload kind of enveloped records, if records are enveloped
+        pub fn envelope_kind(&self) -> Option<PayloadKind> {
+            self.format.envelope_kind
+        }
+
+        /// Read the next raw record; `Ok(None)` at end of stream
+        pub async fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+            match self.read_frame().await? {
+                Some(frame) => frame.decode(self.format).map(Some),
+                None => Ok(None),
+            }
+        }
+
+        /// Read and deserialize the next bincode record; `Ok(None)` at end of stream
+        pub async fn read<T: serde::de::DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+            match self.read_record().await? {
+                Some(bytes) => crate::io::from_bincode(&bytes).map(Some),
+                None => Ok(None),
+            }
+        }
+
+        /// Stream of the remaining records, deserialized from bincode
+        ///
+        /// Error handling matches [`FramedRecords`](super::FramedRecords).
+        pub fn into_stream<T: serde::de::DeserializeOwned>(
+            self,
+        ) -> impl Stream<Item = io::Result<T>> {
+            stream::unfold(Some(self), |state| async move {
+                let mut reader = state?;
+                match reader.read_frame().await {
+                    Ok(Some(frame)) => {
+                        let item = frame
+                            .decode(reader.format)
+                            .and_then(|bytes| crate::io::from_bincode(&bytes));
+                        Some((item, Some(reader)))
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some((Err(e), None)),
+                }
+            })
+        }
+
+        /// Number of records read so far, including bad ones
+        pub fn records_read(&self) -> u64 {
+            self.records
+        }
+
+        /// Return the underlying reader
+        pub fn into_inner(self) -> R {
+            self.reader
+        }
+
+        async fn read_frame(&mut self) -> io::Result<Option<RawFrame>> {
+            let prefix_len = self.format.prefix_len();
+            let mut prefix = [0u8; 8];
+            let mut filled = 0;
+            while filled < prefix_len {
+                let n = self.reader.read(&mut prefix[filled..prefix_len]).await?;
+                if n == 0 {
+                    if filled == 0 {
+                        return Ok(None);
+                    }
+                    return Err(truncated("record prefix"));
+                }
+                filled += n;
+            }
+
+            let (len, crc) = decode_prefix(self.format, &prefix);
+            let mut payload = Vec::new();
+            (&mut self.reader)
+                .take(len)
+                .read_to_end(&mut payload)
+                .await?;
+            if payload.len() as u64 != len {
+                return Err(truncated("record"));
+            }
+            let index = self.records;
+            self.records += 1;
+            Ok(Some(RawFrame {
+                index,
+                crc,
+                payload,
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::CompressionCodec;
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_bad_record_is_skipped() {
+        let mut writer = FramedWriter::new(Vec::new()).unwrap();
+        for i in 0..3u32 {
+            writer.write(&i).unwrap();
+        }
+        let mut wire = writer.finish().unwrap();
+
+        // Flip a payload byte of the second record (header + 1 record of 12 bytes + prefix)
+        wire[STREAM_HEADER_LEN + 12 + 8] ^= 0xff;
+
+        let results: Vec<io::Result<u32>> =
+            FramedReader::new(&wire[..]).unwrap().records().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &0);
+        assert_eq!(
+            results[1].as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(results[2].as_ref().unwrap(), &2);
+    }
+
+    #[test]
+    fn test_truncated_stream_ends_iteration() {
+        let mut writer = FramedWriter::new(Vec::new()).unwrap();
+        writer.write(&1u64).unwrap();
+        writer.write(&2u64).unwrap();
+        let wire = writer.finish().unwrap();
+
+        let mut records = FramedReader::new(&wire[..wire.len() - 3])
+            .unwrap()
+            .records::<u64>();
+        assert_eq!(records.next().unwrap().unwrap(), 1);
+        let err = records.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_enveloped_records_without_checksum() {
+        let opts = FrameOptions {
+            checksum: false,
+            envelope: Some(BinaryWriteOptions {
+                codec: CompressionCodec::None,
+                level: None,
+            }),
+            kind: PayloadKind::EngramBincode,
+        };
+        let mut writer = FramedWriter::with_options(Vec::new(), opts).unwrap();
+        writer.write_record(b"one").unwrap();
+        writer.write_record(b"").unwrap();
+        assert_eq!(writer.records_written(), 2);
+        let wire = writer.finish().unwrap();
+
+        let mut reader = FramedReader::new(&wire[..]).unwrap();
+        assert!(!reader.has_checksum());
+        assert_eq!(reader.envelope_kind(), Some(PayloadKind::EngramBincode));
+        assert_eq!(reader.read_record().unwrap().unwrap(), b"one");
+        assert_eq!(reader.read_record().unwrap().unwrap(), b"");
+        assert!(reader.read_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rejects_foreign_stream() {
+        let err = FramedReader::new(&b"not framed"[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_framed_roundtrip() {
+        use super::async_framed::{AsyncFramedReader, AsyncFramedWriter};
+        use futures::TryStreamExt;
+
+        let mut writer = AsyncFramedWriter::new(Vec::new()).await.unwrap();
+        writer.write(&"a".to_string()).await.unwrap();
+        writer.write(&"b".to_string()).await.unwrap();
+        let wire = writer.finish().await.unwrap();
+
+        // Async output is readable by the sync reader
+        let sync: Vec<String> = FramedReader::new(&wire[..])
+            .unwrap()
+            .records()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(sync, vec!["a", "b"]);
+
+        let records: Vec<String> = AsyncFramedReader::new(&wire[..])
+            .await
+            .unwrap()
+            .into_stream()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(records, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,769 @@
+//! Filesystem image packing using compression profiles
+//!
+//! Walks a directory tree and packs it into a single image file. Each regular
+//! file is stream-compressed with the profile `CompressionProfiler::for_path`
+//! selects for its path inside the tree (so `etc/passwd` is packed like
+//! `/etc/passwd`), and the tree's metadata (mode, ownership, mtime, symlink
+//! targets and extended attributes) is recorded in an index so the tree can be
+//! restored exactly.
+//!
+//! Requires the `fs-image` feature and a Unix platform. Regular files,
+//! directories and symlinks are supported; hard links are packed as separate
+//! files and special files (devices, FIFOs, sockets) are skipped.
+//!
+//! ## Layout
+//!
+//! ```text
+//! +-----------------+  "EDNI" magic, u16 version, u16 reserved
+//! | header (8)      |
+//! +-----------------+
+//! | file data ...   |  one compressed stream per regular file
+//! +-----------------+
+//! | index           |  envelope of the bincode-encoded node list
+//! +-----------------+
+//! | trailer (20)    |  u64 index offset, u64 index length, "EDNI"
+//! +-----------------+
+//! ```
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{pack_fs_image, unpack_fs_image, PackOptions, UnpackOptions};
+//!
+//! let stats = pack_fs_image("rootfs/", "rootfs.edni", &PackOptions::default()).unwrap();
+//! println!("{} files, {} -> {} bytes", stats.files, stats.raw_bytes, stats.stored_bytes);
+//!
+//! unpack_fs_image("rootfs.edni", "restored/", &UnpackOptions::default()).unwrap();
+//! ```
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::atomic::AtomicFile;
+use super::buffer::DEFAULT_BUFFER_SIZE;
+use super::envelope::{unwrap_auto, wrap_or_legacy, CompressionCodec, PayloadKind};
+use super::profiles::{CompressionProfiler, PROFILE_BALANCED};
+use super::stream_compress::{stream_compress, stream_decompress, CompressionLevel};
+
+const IMAGE_MAGIC: [u8; 4] = *b"EDNI";
+const IMAGE_VERSION: u16 = 1;
+const IMAGE_HEADER_LEN: u64 = 8;
+const IMAGE_TRAILER_LEN: u64 = 20;
+
+/// Type-specific data for an image node
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FsNodeKind {
+    /// Directory
+    Dir,
+    /// Regular file stored as a compressed stream in the image
+    File {
+        /// Offset of the compressed stream in the image
+        offset: u64,
+        /// Length of the compressed stream
+        stored_len: u64,
+        /// Uncompressed file size
+        size: u64,
+        /// Codec used for the stream (`CompressionCodec` discriminant)
+        codec: u8,
+        /// Name of the compression profile that was applied
+        profile: String,
+    },
+    /// Symbolic link with its raw target
+    Symlink {
+        /// Link target bytes
+        target: Vec<u8>,
+    },
+}
+
+/// One entry of the image index
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsNode {
+    /// Path relative to the packed root as raw bytes (empty for the root itself)
+    pub path: Vec<u8>,
+    /// Node type and data location
+    pub kind: FsNodeKind,
+    /// Full `st_mode` (type and permission bits)
+    pub mode: u32,
+    /// Owner user id
+    pub uid: u32,
+    /// Owner group id
+    pub gid: u32,
+    /// Modification time, seconds since the Unix epoch
+    pub mtime: i64,
+    /// Modification time, nanosecond part
+    pub mtime_nsec: u32,
+    /// Extended attributes as (name, value) pairs
+    pub xattrs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl FsNode {
+    /// Path relative to the image root
+    pub fn relative_path(&self) -> PathBuf {
+        PathBuf::from(OsStr::from_bytes(&self.path))
+    }
+}
+
+/// Options for [`pack_fs_image`]
+#[derive(Clone, Debug)]
+pub struct PackOptions {
+    /// Profiler used to choose a codec per file
+    pub profiler: CompressionProfiler,
+    /// Record extended attributes
+    pub xattrs: bool,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self {
+            profiler: CompressionProfiler::default(),
+            xattrs: true,
+        }
+    }
+}
+
+/// Options for [`unpack_fs_image`]
+#[derive(Clone, Copy, Debug)]
+pub struct UnpackOptions {
+    /// Restore owner uid/gid
+    ///
+    /// Off by default because changing ownership requires root (or
+    /// `CAP_CHOWN`); enable it when unpacking as root.
+    pub ownership: bool,
+    /// Restore extended attributes
+    pub xattrs: bool,
+}
+
+impl Default for UnpackOptions {
+    fn default() -> Self {
+        Self {
+            ownership: false,
+            xattrs: true,
+        }
+    }
+}
+
+/// Counters reported by packing and unpacking
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FsImageStats {
+    /// Regular files
+    pub files: u64,
+    /// Directories (including the root)
+    pub dirs: u64,
+    /// Symbolic links
+    pub symlinks: u64,
+    /// Special files that were skipped
+    pub skipped: u64,
+    /// Total uncompressed size of regular files
+    pub raw_bytes: u64,
+    /// Total compressed size of regular files
+    pub stored_bytes: u64,
+}
+
+/// Pack the tree under `root` into a single image file
+pub fn pack_fs_image<P: AsRef<Path>, Q: AsRef<Path>>(
+    root: P,
+    image_path: Q,
+    opts: &PackOptions,
+) -> io::Result<FsImageStats> {
+    let root = root.as_ref();
+    let mut out = CountingWriter {
+        inner: BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, AtomicFile::create(image_path)?),
+        written: 0,
+    };
+
+    let mut header = [0u8; IMAGE_HEADER_LEN as usize];
+    header[..4].copy_from_slice(&IMAGE_MAGIC);
+    header[4..6].copy_from_slice(&IMAGE_VERSION.to_le_bytes());
+    out.write_all(&header)?;
+
+    let mut nodes = Vec::new();
+    let mut stats = FsImageStats::default();
+    pack_node(root, Vec::new(), opts, &mut out, &mut nodes, &mut stats)?;
+
+    let index = bincode::serialize(&nodes).map_err(io::Error::other)?;
+    let mut index_opts = PROFILE_BALANCED.to_write_options();
+    if !index_opts.codec.is_available() {
+        index_opts.codec = CompressionCodec::None;
+    }
+    let index = wrap_or_legacy(PayloadKind::EngramBincode, index_opts, &index)?;
+
+    let index_offset = out.written;
+    out.write_all(&index)?;
+    out.write_all(&index_offset.to_le_bytes())?;
+    out.write_all(&(index.len() as u64).to_le_bytes())?;
+    out.write_all(&IMAGE_MAGIC)?;
+    out.inner
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .commit()?;
+
+    Ok(stats)
+}
+
+fn pack_node(
+    path: &Path,
+    rel: Vec<u8>,
+    opts: &PackOptions,
+    out: &mut CountingWriter<BufWriter<AtomicFile>>,
+    nodes: &mut Vec<FsNode>,
+    stats: &mut FsImageStats,
+) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    let file_type = meta.file_type();
+
+    let kind = if file_type.is_dir() {
+        stats.dirs += 1;
+        FsNodeKind::Dir
+    } else if file_type.is_symlink() {
+        stats.symlinks += 1;
+        FsNodeKind::Symlink {
+            target: fs::read_link(path)?.as_os_str().as_bytes().to_vec(),
+        }
+    } else if file_type.is_file() {
+        let mut profile_path = b"/".to_vec();
+        profile_path.extend_from_slice(&rel);
+        let profile = opts
+            .profiler
+            .for_path(&String::from_utf8_lossy(&profile_path));
+        let (codec, level) = if profile.codec.is_available() {
+            (
+                profile.codec,
+                profile
+                    .level
+                    .map_or(CompressionLevel::Default, CompressionLevel::Custom),
+            )
+        } else {
+            (CompressionCodec::None, CompressionLevel::Default)
+        };
+
+        let offset = out.written;
+        let size = stream_compress(
+            File::open(path)?,
+            &mut *out,
+            codec,
+            level,
+            DEFAULT_BUFFER_SIZE,
+        )?;
+        let stored_len = out.written - offset;
+
+        stats.files += 1;
+        stats.raw_bytes += size;
+        stats.stored_bytes += stored_len;
+        FsNodeKind::File {
+            offset,
+            stored_len,
+            size,
+            codec: codec as u8,
+            profile: profile.name.to_string(),
+        }
+    } else {
+        stats.skipped += 1;
+        return Ok(());
+    };
+
+    let xattrs = if opts.xattrs {
+        read_xattrs(path)?
+    } else {
+        Vec::new()
+    };
+
+    nodes.push(FsNode {
+        path: rel.clone(),
+        kind,
+        mode: meta.mode(),
+        uid: meta.uid(),
+        gid: meta.gid(),
+        mtime: meta.mtime(),
+        mtime_nsec: meta.mtime_nsec() as u32,
+        xattrs,
+    });
+
+    if file_type.is_dir() {
+        let mut children = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        children.sort_by_key(|e| e.file_name());
+        for child in children {
+            let mut child_rel = rel.clone();
+            if !child_rel.is_empty() {
+                child_rel.push(b'/');
+            }
+            child_rel.extend_from_slice(child.file_name().as_bytes());
+            pack_node(&child.path(), child_rel, opts, out, nodes, stats)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_xattrs(path: &Path) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let names = match xattr::list(path) {
+        Ok(names) => names,
+        // Filesystem without xattr support
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut xattrs = Vec::new();
+    for name in names {
+        if let Some(value) = xattr::get(path, &name)? {
+            xattrs.push((name.as_bytes().to_vec(), value));
+        }
+    }
+    xattrs.sort();
+    Ok(xattrs)
+}
+
+/// Read the node index of an image without extracting it
+pub fn read_fs_image_index<P: AsRef<Path>>(image_path: P) -> io::Result<Vec<FsNode>> {
+    let mut image = File::open(image_path)?;
+    read_index(&mut image)
+}
+
+fn read_index(image: &mut File) -> io::Result<Vec<FsNode>> {
+    let len = image.seek(SeekFrom::End(0))?;
+    if len < IMAGE_HEADER_LEN + IMAGE_TRAILER_LEN {
+        return Err(invalid("filesystem image too short"));
+    }
+
+    let mut header = [0u8; IMAGE_HEADER_LEN as usize];
+    image.seek(SeekFrom::Start(0))?;
+    image.read_exact(&mut header)?;
+    if header[..4] != IMAGE_MAGIC {
+        return Err(invalid("not a filesystem image (bad magic)"));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != IMAGE_VERSION {
+        return Err(invalid("unsupported filesystem image version"));
+    }
+
+    let mut trailer = [0u8; IMAGE_TRAILER_LEN as usize];
+    image.seek(SeekFrom::Start(len - IMAGE_TRAILER_LEN))?;
+    image.read_exact(&mut trailer)?;
+    if trailer[16..] != IMAGE_MAGIC {
+        return Err(invalid("filesystem image trailer missing"));
+    }
+    let index_offset = u64::from_le_bytes(trailer[..8].try_into().expect("fixed slice"));
+    let index_len = u64::from_le_bytes(trailer[8..16].try_into().expect("fixed slice"));
+    if index_offset < IMAGE_HEADER_LEN
+        || index_offset.checked_add(index_len) != Some(len - IMAGE_TRAILER_LEN)
+    {
+        return Err(invalid("filesystem image index out of bounds"));
+    }
+
+    let mut index = vec![0u8; index_len as usize];
+    image.seek(SeekFrom::Start(index_offset))?;
+    image.read_exact(&mut index)?;
+    let index = unwrap_auto(PayloadKind::EngramBincode, &index)?;
+    let nodes: Vec<FsNode> = bincode::deserialize(&index).map_err(io::Error::other)?;
+
+    for node in &nodes {
+        if let FsNodeKind::File {
+            offset, stored_len, ..
+        } = node.kind
+        {
+            let end = offset.checked_add(stored_len);
+            if offset < IMAGE_HEADER_LEN || end.is_none_or(|end| end > index_offset) {
+                return Err(invalid("filesystem image file data out of bounds"));
+            }
+        }
+    }
+    check_tree(&nodes)?;
+
+    Ok(nodes)
+}
+
+/// Check that the index describes a tree that can be restored safely
+///
+/// Every path must be unique and every node other than the root must follow
+/// the directory node of its parent. Together with `safe_join` this means no
+/// node can be placed below a symlink from the same image.
+fn check_tree(nodes: &[FsNode]) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    let mut dirs = HashSet::new();
+
+    for (i, node) in nodes.iter().enumerate() {
+        let path = node.path.as_slice();
+        if !seen.insert(path) {
+            return Err(invalid("duplicate path in filesystem image"));
+        }
+        if path.is_empty() {
+            if i != 0 {
+                return Err(invalid("filesystem image root is not the first node"));
+            }
+        } else {
+            let parent = path
+                .iter()
+                .rposition(|&b| b == b'/')
+                .map_or(&path[..0], |pos| &path[..pos]);
+            if !dirs.contains(parent) {
+                return Err(invalid(
+                    "filesystem image node is not below a directory node",
+                ));
+            }
+        }
+        if node.kind == FsNodeKind::Dir {
+            dirs.insert(path);
+        }
+    }
+    Ok(())
+}
+
+/// Restore the tree stored in an image under `dest`
+///
+/// `dest` is created if needed and receives the metadata of the packed root.
+/// Existing entries are never replaced: restoring a file or symlink onto a
+/// path that already exists fails, and a directory is only reused if it is a
+/// real directory rather than a symlink to one. Symlinks are created after
+/// all files and directories, so nothing is ever written through them.
+pub fn unpack_fs_image<P: AsRef<Path>, Q: AsRef<Path>>(
+    image_path: P,
+    dest: Q,
+    opts: &UnpackOptions,
+) -> io::Result<FsImageStats> {
+    let dest = dest.as_ref();
+    let mut image = File::open(image_path)?;
+    let nodes = read_index(&mut image)?;
+
+    let mut stats = FsImageStats::default();
+    let mut dirs = Vec::new();
+    let mut links = Vec::new();
+
+    for node in &nodes {
+        let target = safe_join(dest, &node.path)?;
+
+        match &node.kind {
+            FsNodeKind::Dir => {
+                if node.path.is_empty() {
+                    fs::create_dir_all(&target)?;
+                } else if !fs::symlink_metadata(&target).is_ok_and(|m| m.is_dir()) {
+                    // Fails if anything other than a real directory is there
+                    fs::create_dir(&target)?;
+                }
+                stats.dirs += 1;
+                // Directory metadata is applied once all children exist
+                dirs.push((node, target));
+            }
+            FsNodeKind::Symlink { .. } => links.push((node, target)),
+            FsNodeKind::File {
+                offset,
+                stored_len,
+                size,
+                codec,
+                ..
+            } => {
+                let codec = CompressionCodec::from_u8(*codec)
+                    .ok_or_else(|| invalid("unknown codec in filesystem image"))?;
+                image.seek(SeekFrom::Start(*offset))?;
+                let input = BufReader::new((&mut image).take(*stored_len));
+                let output = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .custom_flags(libc::O_NOFOLLOW)
+                    .open(&target)?;
+                let written = stream_decompress(input, &output, codec, DEFAULT_BUFFER_SIZE)?;
+                if written != *size {
+                    return Err(invalid("filesystem image file size mismatch"));
+                }
+                stats.files += 1;
+                stats.raw_bytes += size;
+                stats.stored_bytes += stored_len;
+                apply_metadata(node, &target, opts)?;
+            }
+        }
+    }
+
+    for (node, target) in links {
+        if let FsNodeKind::Symlink { target: link } = &node.kind {
+            std::os::unix::fs::symlink(OsStr::from_bytes(link), &target)?;
+        }
+        stats.symlinks += 1;
+        apply_metadata(node, &target, opts)?;
+    }
+
+    // Deepest directories first so parent mtimes are not disturbed afterwards
+    for (node, target) in dirs.into_iter().rev() {
+        apply_metadata(node, &target, opts)?;
+    }
+
+    Ok(stats)
+}
+
+fn apply_metadata(node: &FsNode, target: &Path, opts: &UnpackOptions) -> io::Result<()> {
+    let is_symlink = matches!(node.kind, FsNodeKind::Symlink { .. });
+
+    if opts.xattrs {
+        for (name, value) in &node.xattrs {
+            xattr::set(target, OsStr::from_bytes(name), value)?;
+        }
+    }
+    if opts.ownership {
+        std::os::unix::fs::lchown(target, Some(node.uid), Some(node.gid))?;
+    }
+    if !is_symlink {
+        fs::set_permissions(target, fs::Permissions::from_mode(node.mode & 0o7777))?;
+    }
+
+    let mtime = filetime::FileTime::from_unix_time(node.mtime, node.mtime_nsec);
+    filetime::set_symlink_file_times(target, mtime, mtime)
+}
+
+/// Join an image-relative path onto `dest`, rejecting absolute paths and `..`
+fn safe_join(dest: &Path, rel: &[u8]) -> io::Result<PathBuf> {
+    let rel = Path::new(OsStr::from_bytes(rel));
+    let mut out = dest.to_path_buf();
+    for component in rel.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return Err(invalid("unsafe path in filesystem image")),
+        }
+    }
+    Ok(out)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Writer adapter tracking how many bytes have been written
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    fn build_tree(root: &Path) {
+        fs::create_dir_all(root.join("etc/app")).unwrap();
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::write(root.join("etc/app/app.conf"), b"key = value\n".repeat(50)).unwrap();
+        fs::write(root.join("usr/lib/libdemo.so.1"), vec![0x7f; 4096]).unwrap();
+        fs::write(root.join("empty"), b"").unwrap();
+        std::os::unix::fs::symlink("libdemo.so.1", root.join("usr/lib/libdemo.so")).unwrap();
+
+        fs::set_permissions(
+            root.join("etc/app/app.conf"),
+            fs::Permissions::from_mode(0o640),
+        )
+        .unwrap();
+        fs::set_permissions(root.join("usr/lib"), fs::Permissions::from_mode(0o750)).unwrap();
+
+        let old = filetime::FileTime::from_unix_time(1_600_000_000, 123_000_000);
+        filetime::set_file_mtime(root.join("usr/lib/libdemo.so.1"), old).unwrap();
+        filetime::set_symlink_file_times(root.join("usr/lib/libdemo.so"), old, old).unwrap();
+        filetime::set_file_mtime(root.join("usr/lib"), old).unwrap();
+    }
+
+    #[test]
+    fn test_pack_unpack_roundtrip() {
+        let src = tempdir().unwrap();
+        let out = tempdir().unwrap();
+        let root = src.path().join("root");
+        build_tree(&root);
+        let has_xattr = xattr::set(root.join("empty"), "user.engram", b"tag").is_ok();
+
+        let image = src.path().join("root.edni");
+        let packed = pack_fs_image(&root, &image, &PackOptions::default()).unwrap();
+        assert_eq!(packed.files, 3);
+        assert_eq!(packed.dirs, 5);
+        assert_eq!(packed.symlinks, 1);
+
+        let dest = out.path().join("restored");
+        let unpacked = unpack_fs_image(&image, &dest, &UnpackOptions::default()).unwrap();
+        assert_eq!(unpacked.files, packed.files);
+        assert_eq!(unpacked.raw_bytes, packed.raw_bytes);
+
+        for rel in ["etc/app/app.conf", "usr/lib/libdemo.so.1", "empty"] {
+            assert_eq!(
+                fs::read(root.join(rel)).unwrap(),
+                fs::read(dest.join(rel)).unwrap()
+            );
+        }
+        assert_eq!(
+            fs::read_link(dest.join("usr/lib/libdemo.so")).unwrap(),
+            Path::new("libdemo.so.1")
+        );
+
+        let mode = |p: &Path| fs::symlink_metadata(p).unwrap().mode();
+        let mtime = |p: &Path| fs::symlink_metadata(p).unwrap().modified().unwrap();
+        for rel in [
+            "",
+            "etc/app/app.conf",
+            "usr/lib",
+            "usr/lib/libdemo.so.1",
+            "usr/lib/libdemo.so",
+        ] {
+            assert_eq!(
+                mode(&root.join(rel)),
+                mode(&dest.join(rel)),
+                "mode of {rel}"
+            );
+            assert_eq!(
+                mtime(&root.join(rel)),
+                mtime(&dest.join(rel)),
+                "mtime of {rel}"
+            );
+        }
+        assert_eq!(
+            mtime(&dest.join("usr/lib/libdemo.so.1")),
+            UNIX_EPOCH + Duration::new(1_600_000_000, 123_000_000)
+        );
+
+        if has_xattr {
+            assert_eq!(
+                xattr::get(dest.join("empty"), "user.engram").unwrap(),
+                Some(b"tag".to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_records_profiles() {
+        let src = tempdir().unwrap();
+        let root = src.path().join("root");
+        build_tree(&root);
+
+        let image = src.path().join("root.edni");
+        pack_fs_image(&root, &image, &PackOptions::default()).unwrap();
+
+        let nodes = read_fs_image_index(&image).unwrap();
+        assert!(nodes[0].path.is_empty());
+        let profile_of = |rel: &str| {
+            nodes
+                .iter()
+                .find(|n| n.path == rel.as_bytes())
+                .and_then(|n| match &n.kind {
+                    FsNodeKind::File { profile, .. } => Some(profile.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(profile_of("etc/app/app.conf"), "Config");
+        assert_eq!(profile_of("usr/lib/libdemo.so.1"), "Libraries");
+    }
+
+    fn node(path: &str, kind: FsNodeKind) -> FsNode {
+        FsNode {
+            path: path.as_bytes().to_vec(),
+            kind,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            mtime: 0,
+            mtime_nsec: 0,
+            xattrs: Vec::new(),
+        }
+    }
+
+    /// Write an image with a hand-built index and `data` as its only file data
+    fn write_raw_image(path: &Path, data: &[u8], nodes: &[FsNode]) {
+        let index = bincode::serialize(nodes).unwrap();
+        let index_offset = IMAGE_HEADER_LEN + data.len() as u64;
+        let mut image = IMAGE_MAGIC.to_vec();
+        image.extend_from_slice(&IMAGE_VERSION.to_le_bytes());
+        image.extend_from_slice(&[0, 0]);
+        image.extend_from_slice(data);
+        image.extend_from_slice(&index);
+        image.extend_from_slice(&index_offset.to_le_bytes());
+        image.extend_from_slice(&(index.len() as u64).to_le_bytes());
+        image.extend_from_slice(&IMAGE_MAGIC);
+        fs::write(path, image).unwrap();
+    }
+
+    #[test]
+    fn test_symlink_escape_rejected() {
+        let dir = tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("victim"), b"original").unwrap();
+
+        let file = |path: &str| {
+            node(
+                path,
+                FsNodeKind::File {
+                    offset: IMAGE_HEADER_LEN,
+                    stored_len: 5,
+                    size: 5,
+                    codec: CompressionCodec::None as u8,
+                    profile: String::new(),
+                },
+            )
+        };
+        let link = |path: &str, target: &Path| {
+            node(
+                path,
+                FsNodeKind::Symlink {
+                    target: target.as_os_str().as_bytes().to_vec(),
+                },
+            )
+        };
+        let root = node("", FsNodeKind::Dir);
+
+        let cases = [
+            // File written through a symlinked parent directory
+            vec![root.clone(), link("d", &outside), file("d/victim")],
+            // File written onto an existing symlink
+            vec![root.clone(), link("d", &outside.join("victim")), file("d")],
+            // Node placed before its parent directory
+            vec![root.clone(), file("d/victim"), node("d", FsNodeKind::Dir)],
+        ];
+        for (i, nodes) in cases.iter().enumerate() {
+            let image = dir.path().join(format!("evil{i}.edni"));
+            write_raw_image(&image, b"owned", nodes);
+            let err = unpack_fs_image(
+                &image,
+                dir.path().join(format!("dest{i}")),
+                &UnpackOptions::default(),
+            )
+            .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "case {i}");
+        }
+
+        // A symlink already present in the destination is not followed either
+        let dest = dir.path().join("existing");
+        fs::create_dir(&dest).unwrap();
+        std::os::unix::fs::symlink(outside.join("victim"), dest.join("f")).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("d")).unwrap();
+        let image = dir.path().join("plain.edni");
+        write_raw_image(&image, b"owned", &[root.clone(), file("f")]);
+        assert!(unpack_fs_image(&image, &dest, &UnpackOptions::default()).is_err());
+        write_raw_image(
+            &image,
+            b"owned",
+            &[root, node("d", FsNodeKind::Dir), file("d/victim")],
+        );
+        assert!(unpack_fs_image(&image, &dest, &UnpackOptions::default()).is_err());
+
+        assert_eq!(fs::read(outside.join("victim")).unwrap(), b"original");
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_unsafe_paths_rejected() {
+        let dest = Path::new("/tmp/dest");
+        assert!(safe_join(dest, b"../escape").is_err());
+        assert!(safe_join(dest, b"/etc/passwd").is_err());
+        assert_eq!(safe_join(dest, b"a/b").unwrap(), dest.join("a/b"));
+    }
+}
@@ -0,0 +1,394 @@
+//! Streaming hash adapters
+//!
+//! [`HashingReader`] and [`HashingWriter`] compute a digest of the data
+//! passing through them, so a file can be copied, written or compressed and
+//! hashed in a single pass. Each algorithm is behind its own feature flag
+//! (`hash-blake3`, `hash-sha256`, `hash-xxhash`); selecting one that was not
+//! compiled in returns an error, as with compression codecs.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{copy_buffered, HashAlgorithm, HashingReader};
+//! use std::fs::File;
+//!
+//! let src = File::open("input.bin").unwrap();
+//! let mut dst = File::create("output.bin").unwrap();
+//! let mut reader = HashingReader::new(src, HashAlgorithm::Blake3).unwrap();
+//! copy_buffered(&mut reader, &mut dst, 64 * 1024).unwrap();
+//!
+//! let (_, digest, bytes) = reader.finish();
+//! println!("{bytes} bytes, blake3 {digest}");
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use super::atomic::AtomicFile;
+use super::envelope::CompressionCodec;
+use super::stream::StreamWriter;
+use super::stream_compress::{stream_compress, CompressionLevel};
+
+/// Supported digest algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// BLAKE3 (32 bytes)
+    Blake3,
+    /// SHA-256 (32 bytes)
+    Sha256,
+    /// XXH3 64-bit (8 bytes, big-endian); fast but not cryptographic
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Short lowercase name
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+            Self::Xxh3 => "xxh3",
+        }
+    }
+
+    /// Parse a name as returned by [`HashAlgorithm::name`] (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "blake3" => Some(Self::Blake3),
+            "sha256" | "sha-256" => Some(Self::Sha256),
+            "xxh3" | "xxhash" => Some(Self::Xxh3),
+            _ => None,
+        }
+    }
+
+    /// Whether support for this algorithm is compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Blake3 => cfg!(feature = "hash-blake3"),
+            Self::Sha256 => cfg!(feature = "hash-sha256"),
+            Self::Xxh3 => cfg!(feature = "hash-xxhash"),
+        }
+    }
+}
+
+/// Digest produced by a [`Hasher`]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Digest {
+    algorithm: HashAlgorithm,
+    bytes: Vec<u8>,
+}
+
+impl Digest {
+    /// Algorithm that produced the digest
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Raw digest bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Lowercase hex encoding
+    pub fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Digest({}:{})", self.algorithm.name(), self.to_hex())
+    }
+}
+
+/// Incremental hasher for any [`HashAlgorithm`]
+pub struct Hasher {
+    inner: HasherInner,
+}
+
+enum HasherInner {
+    #[cfg(feature = "hash-blake3")]
+    Blake3(Box<blake3::Hasher>),
+    #[cfg(feature = "hash-sha256")]
+    Sha256(sha2::Sha256),
+    #[cfg(feature = "hash-xxhash")]
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+// With no hash feature enabled `HasherInner` is empty and every method diverges
+#[cfg_attr(
+    not(any(
+        feature = "hash-blake3",
+        feature = "hash-sha256",
+        feature = "hash-xxhash"
+    )),
+    allow(unreachable_code, unused_variables)
+)]
+impl Hasher {
+    /// Create a hasher, failing if the algorithm is not compiled in
+    pub fn new(algorithm: HashAlgorithm) -> io::Result<Self> {
+        let inner = match algorithm {
+            #[cfg(feature = "hash-blake3")]
+            HashAlgorithm::Blake3 => HasherInner::Blake3(Box::default()),
+            #[cfg(feature = "hash-sha256")]
+            HashAlgorithm::Sha256 => {
+                use sha2::Digest as _;
+                HasherInner::Sha256(sha2::Sha256::new())
+            }
+            #[cfg(feature = "hash-xxhash")]
+            HashAlgorithm::Xxh3 => HasherInner::Xxh3(Box::default()),
+            #[allow(unreachable_patterns)]
+            other => {
+                return Err(io::Error::other(format!(
+                    "{} hashing support not enabled (enable feature `{}`)",
+                    other.name(),
+                    match other {
+                        HashAlgorithm::Blake3 => "hash-blake3",
+                        HashAlgorithm::Sha256 => "hash-sha256",
+                        HashAlgorithm::Xxh3 => "hash-xxhash",
+                    }
+                )))
+            }
+        };
+        Ok(Self { inner })
+    }
+
+    /// Algorithm used by this hasher
+    pub fn algorithm(&self) -> HashAlgorithm {
+        match self.inner {
+            #[cfg(feature = "hash-blake3")]
+            HasherInner::Blake3(_) => HashAlgorithm::Blake3,
+            #[cfg(feature = "hash-sha256")]
+            HasherInner::Sha256(_) => HashAlgorithm::Sha256,
+            #[cfg(feature = "hash-xxhash")]
+            HasherInner::Xxh3(_) => HashAlgorithm::Xxh3,
+        }
+    }
+
+    /// Feed more data
+    pub fn update(&mut self, _data: &[u8]) {
+        match self.inner {
+            #[cfg(feature = "hash-blake3")]
+            HasherInner::Blake3(ref mut h) => {
+                h.update(_data);
+            }
+            #[cfg(feature = "hash-sha256")]
+            HasherInner::Sha256(ref mut h) => sha2::Digest::update(h, _data),
+            #[cfg(feature = "hash-xxhash")]
+            HasherInner::Xxh3(ref mut h) => h.update(_data),
+        }
+    }
+
+    /// Produce the digest of all data fed so far
+    pub fn finalize(self) -> Digest {
+        let algorithm = self.algorithm();
+        let bytes = match self.inner {
+            #[cfg(feature = "hash-blake3")]
+            HasherInner::Blake3(h) => h.finalize().as_bytes().to_vec(),
+            #[cfg(feature = "hash-sha256")]
+            HasherInner::Sha256(h) => sha2::Digest::finalize(h).to_vec(),
+            #[cfg(feature = "hash-xxhash")]
+            HasherInner::Xxh3(h) => h.digest().to_be_bytes().to_vec(),
+        };
+        Digest { algorithm, bytes }
+    }
+}
+
+/// Hash `data` in one call
+pub fn hash_bytes(algorithm: HashAlgorithm, data: &[u8]) -> io::Result<Digest> {
+    let mut hasher = Hasher::new(algorithm)?;
+    hasher.update(data);
+    Ok(hasher.finalize())
+}
+
+/// Reader adapter that hashes everything read through it
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+    bytes: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Wrap `inner`, hashing with `algorithm`
+    pub fn new(inner: R, algorithm: HashAlgorithm) -> io::Result<Self> {
+        Ok(Self {
+            inner,
+            hasher: Hasher::new(algorithm)?,
+            bytes: 0,
+        })
+    }
+
+    /// Bytes read so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Return the inner reader, the digest and the number of bytes read
+    pub fn finish(self) -> (R, Digest, u64) {
+        (self.inner, self.hasher.finalize(), self.bytes)
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// Writer adapter that hashes everything written through it
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Hasher,
+    bytes: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    /// Wrap `inner`, hashing with `algorithm`
+    pub fn new(inner: W, algorithm: HashAlgorithm) -> io::Result<Self> {
+        Ok(Self {
+            inner,
+            hasher: Hasher::new(algorithm)?,
+            bytes: 0,
+        })
+    }
+
+    /// Bytes written so far
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Flush and return the inner writer, the digest and the number of bytes written
+    pub fn finish(mut self) -> io::Result<(W, Digest, u64)> {
+        self.inner.flush()?;
+        Ok((self.inner, self.hasher.finalize(), self.bytes))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.bytes += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write data to a file in streaming fashion, returning the digest and size
+///
+/// Like [`stream_write_file`](super::stream::stream_write_file), hashing the
+/// data as it is written.
+pub fn stream_write_file_with_digest<P, I, D>(
+    path: P,
+    chunks: I,
+    algorithm: HashAlgorithm,
+) -> io::Result<(Digest, u64)>
+where
+    P: AsRef<Path>,
+    I: Iterator<Item = D>,
+    D: AsRef<[u8]>,
+{
+    let file = AtomicFile::create(path)?;
+    let mut writer = StreamWriter::new(HashingWriter::new(file, algorithm)?);
+
+    for chunk in chunks {
+        writer.write_chunk(chunk.as_ref())?;
+    }
+
+    let (file, digest, bytes) = writer.finish()?.finish()?;
+    file.commit()?;
+    Ok((digest, bytes))
+}
+
+/// Stream-compress a file, also returning the digest of the uncompressed data
+///
+/// Like [`compress_file`](super::stream_compress::compress_file); returns
+/// (uncompressed_size, compressed_size, digest).
+pub fn compress_file_with_digest<P: AsRef<Path>, Q: AsRef<Path>>(
+    input_path: P,
+    output_path: Q,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    algorithm: HashAlgorithm,
+) -> io::Result<(u64, u64, Digest)> {
+    let mut input = HashingReader::new(File::open(input_path)?, algorithm)?;
+    let mut output = AtomicFile::create(output_path.as_ref())?;
+
+    stream_compress(&mut input, &mut output, codec, level, 64 * 1024)?;
+    output.commit()?;
+
+    let output_size = std::fs::metadata(output_path)?.len();
+    let (_, digest, input_size) = input.finish();
+    Ok((input_size, output_size, digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algorithm_names() {
+        for algorithm in [
+            HashAlgorithm::Blake3,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Xxh3,
+        ] {
+            assert_eq!(HashAlgorithm::from_name(algorithm.name()), Some(algorithm));
+        }
+        assert_eq!(HashAlgorithm::from_name("md5"), None);
+    }
+
+    #[cfg(not(feature = "hash-sha256"))]
+    #[test]
+    fn test_unavailable_algorithm_errors() {
+        assert!(!HashAlgorithm::Sha256.is_available());
+        assert!(Hasher::new(HashAlgorithm::Sha256).is_err());
+    }
+
+    #[cfg(feature = "hash-sha256")]
+    #[test]
+    fn test_sha256_known_vector() {
+        let digest = hash_bytes(HashAlgorithm::Sha256, b"abc").unwrap();
+        assert_eq!(
+            digest.to_hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[cfg(all(feature = "hash-blake3", feature = "hash-xxhash"))]
+    #[test]
+    fn test_reader_and_writer_match_one_shot() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 253) as u8).collect();
+        for algorithm in [HashAlgorithm::Blake3, HashAlgorithm::Xxh3] {
+            let expected = hash_bytes(algorithm, &data).unwrap();
+
+            let mut reader = HashingReader::new(data.as_slice(), algorithm).unwrap();
+            let mut out = Vec::new();
+            crate::copy_buffered(&mut reader, &mut out, 4096).unwrap();
+            let (_, digest, bytes) = reader.finish();
+            assert_eq!(digest, expected);
+            assert_eq!(bytes, data.len() as u64);
+
+            let mut writer = HashingWriter::new(Vec::new(), algorithm).unwrap();
+            for piece in data.chunks(777) {
+                writer.write_all(piece).unwrap();
+            }
+            let (out, digest, bytes) = writer.finish().unwrap();
+            assert_eq!(out, data);
+            assert_eq!(digest, expected);
+            assert_eq!(bytes, data.len() as u64);
+        }
+    }
+}
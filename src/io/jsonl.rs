@@ -0,0 +1,317 @@
+//! JSON Lines (NDJSON) streaming serialization
+//!
+//! [`JsonLinesWriter`] writes one compact JSON document per line and
+//! [`JsonLinesReader`] reads them back as typed records. Both work over any
+//! `Write`/`Read`, so wrapping a [`StreamCompressor`](super::StreamCompressor)
+//! or [`StreamDecompressor`](super::StreamDecompressor) gives `.jsonl.zst`
+//! (or `.jsonl.lz4`) without a temporary file.
+//!
+//! Blank lines and trailing `\r` are ignored. A line that fails to parse is
+//! reported with its 1-based line number; with
+//! [`skip_malformed`](JsonLinesReader::skip_malformed) it is counted and
+//! skipped instead.
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{JsonLinesReader, JsonLinesWriter};
+//!
+//! let mut writer = JsonLinesWriter::new(Vec::new());
+//! writer.write(&[1, 2]).unwrap();
+//! writer.write(&[3, 4]).unwrap();
+//! let text = writer.finish().unwrap();
+//! assert_eq!(text, b"[1,2]\n[3,4]\n");
+//!
+//! let records: Vec<Vec<u32>> = JsonLinesReader::new(&text[..])
+//!     .records()
+//!     .collect::<std::io::Result<_>>()
+//!     .unwrap();
+//! assert_eq!(records, vec![vec![1, 2], vec![3, 4]]);
+//! ```
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::marker::PhantomData;
+
+/// Writer that emits one JSON document per line
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+    line: Vec<u8>,
+    lines: u64,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    /// Write JSON lines to `writer`
+    ///
+    /// Each record is a single `write_all`; wrap unbuffered writers in a
+    /// `BufWriter` when writing many small records.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            line: Vec::new(),
+            lines: 0,
+        }
+    }
+
+    /// Serialize `value` as compact JSON followed by a newline
+    ///
+    /// The value is serialized before anything is written, so a
+    /// serialization error never leaves a partial line on the stream.
+    pub fn write<T: serde::Serialize>(&mut self, value: &T) -> io::Result<()> {
+        self.line.clear();
+        serde_json::to_writer(&mut self.line, value).map_err(io::Error::other)?;
+        self.line.push(b'\n');
+        self.writer.write_all(&self.line)?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Number of lines written so far
+    pub fn lines_written(&self) -> u64 {
+        self.lines
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush and return the underlying writer
+    ///
+    /// Compressing writers still need their own `finish` afterwards.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reader for JSON Lines streams
+pub struct JsonLinesReader<R: Read> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+    line_number: u64,
+    skip_malformed: bool,
+    skipped: u64,
+}
+
+impl<R: Read> JsonLinesReader<R> {
+    /// Read JSON lines from `reader`, which is buffered internally
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            line: Vec::new(),
+            line_number: 0,
+            skip_malformed: false,
+            skipped: 0,
+        }
+    }
+
+    /// Skip lines that fail to parse instead of returning an error
+    ///
+    /// I/O errors are still returned.
+    pub fn skip_malformed(mut self, skip: bool) -> Self {
+        self.skip_malformed = skip;
+        self
+    }
+
+    /// Line number of the last line read (1-based, 0 before the first read)
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// Number of malformed lines skipped so far
+    pub fn skipped_lines(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Read and deserialize the next record; `Ok(None)` at end of stream
+    ///
+    /// A parse error is `InvalidData` and names the line; the next call
+    /// continues with the following line.
+    pub fn read<T: serde::de::DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+        self.read_record().map_err(|e| match e {
+            RecordError::Io(e) | RecordError::Parse(e) => e,
+        })
+    }
+
+    fn read_record<T: serde::de::DeserializeOwned>(&mut self) -> Result<Option<T>, RecordError> {
+        loop {
+            self.line.clear();
+            if self
+                .reader
+                .read_until(b'\n', &mut self.line)
+                .map_err(RecordError::Io)?
+                == 0
+            {
+                return Ok(None);
+            }
+            self.line_number += 1;
+
+            let mut line = &self.line[..];
+            if let Some(rest) = line.strip_suffix(b"\n") {
+                line = rest;
+            }
+            if let Some(rest) = line.strip_suffix(b"\r") {
+                line = rest;
+            }
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            match serde_json::from_slice(line) {
+                Ok(value) => return Ok(Some(value)),
+                Err(_) if self.skip_malformed => self.skipped += 1,
+                Err(e) => {
+                    return Err(RecordError::Parse(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: {e}", self.line_number),
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Iterate over the remaining records
+    pub fn records<T: serde::de::DeserializeOwned>(self) -> JsonLinesRecords<R, T> {
+        JsonLinesRecords {
+            reader: self,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Return the underlying reader
+    ///
+    /// Input already buffered but not yet parsed is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+/// Error from reading one record, keeping parse errors apart from I/O errors
+///
+/// Both are `InvalidData` in some cases (decompressors report corrupt input
+/// that way), so the error kind alone cannot tell them apart.
+enum RecordError {
+    Io(io::Error),
+    Parse(io::Error),
+}
+
+/// Iterator of typed records from a [`JsonLinesReader`]
+///
+/// Malformed lines yield an error and iteration continues (unless they are
+/// skipped); an I/O error yields an error and ends iteration.
+pub struct JsonLinesRecords<R: Read, T> {
+    reader: JsonLinesReader<R>,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T> JsonLinesRecords<R, T> {
+    /// The reader being iterated, e.g. to check [`JsonLinesReader::skipped_lines`]
+    pub fn reader(&self) -> &JsonLinesReader<R> {
+        &self.reader
+    }
+}
+
+impl<R: Read, T: serde::de::DeserializeOwned> Iterator for JsonLinesRecords<R, T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.read_record() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(RecordError::Parse(e)) => Some(Err(e)),
+            Err(RecordError::Io(e)) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Event {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_malformed_line_reports_line_number() {
+        let text = b"{\"id\":1,\"name\":\"a\"}\r\n\n{\"id\":2,\n{\"id\":3,\"name\":\"c\"}\n";
+        let results: Vec<io::Result<Event>> = JsonLinesReader::new(&text[..]).records().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().id, 1);
+
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 3:"), "{err}");
+        assert_eq!(results[2].as_ref().unwrap().id, 3);
+    }
+
+    #[test]
+    fn test_skip_malformed() {
+        let text = b"not json\n{\"id\":1,\"name\":\"a\"}\n\xff\xfe\n";
+        let mut records = JsonLinesReader::new(&text[..])
+            .skip_malformed(true)
+            .records::<Event>();
+        assert_eq!(records.next().unwrap().unwrap().id, 1);
+        assert!(records.next().is_none());
+        assert_eq!(records.reader().skipped_lines(), 2);
+        assert_eq!(records.reader().line_number(), 3);
+    }
+
+    #[test]
+    fn test_invalid_data_from_reader_ends_iteration() {
+        // Like a decompressor rejecting corrupt input on every call
+        struct Corrupt;
+        impl Read for Corrupt {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt frame"))
+            }
+        }
+
+        let results: Vec<io::Result<Event>> = JsonLinesReader::new(Corrupt).records().collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "compression-zstd")]
+    fn test_jsonl_zst_roundtrip() {
+        use crate::io::{CompressionLevel, StreamCompressor, StreamDecompressor};
+
+        let compressor = StreamCompressor::zstd(Vec::new(), CompressionLevel::Default).unwrap();
+        let mut writer = JsonLinesWriter::new(compressor);
+        for id in 0..100 {
+            writer
+                .write(&Event {
+                    id,
+                    name: format!("event-{id}"),
+                })
+                .unwrap();
+        }
+        assert_eq!(writer.lines_written(), 100);
+        let compressed = writer.finish().unwrap().finish().unwrap();
+
+        let decompressor = StreamDecompressor::zstd(&compressed[..]).unwrap();
+        let events: Vec<Event> = JsonLinesReader::new(decompressor)
+            .records()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(events.len(), 100);
+        assert_eq!(events[42].name, "event-42");
+    }
+}
@@ -0,0 +1,477 @@
+//! Merkle-tree integrity manifests for large files
+//!
+//! A [`MerkleTree`] holds a BLAKE3 hash for every fixed-size block of a file
+//! plus the root hash over all of them. It is stored next to the file in a
+//! sidecar (`<file>.merkle`) as a [`PayloadKind::MerkleTree`] envelope, so a
+//! single block can be checked without reading the rest of the file and a
+//! full verification reports exactly which blocks are corrupt.
+//!
+//! Leaves are `H(0x00 || block)` and inner nodes `H(0x01 || left || right)`;
+//! an odd node at the end of a level is carried up unchanged.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{stream_write_file_with_merkle, verify_merkle_file, DEFAULT_MERKLE_BLOCK_SIZE};
+//!
+//! let chunks: Vec<&[u8]> = vec![b"large ", b"payload"];
+//! stream_write_file_with_merkle("data.bin", chunks.iter().copied(), DEFAULT_MERKLE_BLOCK_SIZE)
+//!     .unwrap();
+//!
+//! let report = verify_merkle_file("data.bin").unwrap();
+//! assert!(report.is_ok(), "corrupt blocks: {:?}", report.corrupt_blocks);
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::atomic::{write_atomic, AtomicFile};
+use super::envelope::{unwrap_auto, wrap, BinaryWriteOptions, CompressionCodec, PayloadKind};
+use super::serialize::{from_bincode, to_bincode};
+use super::stream::StreamWriter;
+use super::stream_compress::{stream_compress, CompressionLevel};
+
+/// Default block size for Merkle trees (1MB)
+pub const DEFAULT_MERKLE_BLOCK_SIZE: u32 = 1024 * 1024;
+
+/// Hash of a block or tree node
+pub type MerkleHash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Block hashes and root hash for one file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleTree {
+    block_size: u32,
+    data_len: u64,
+    leaves: Vec<MerkleHash>,
+    root: MerkleHash,
+}
+
+impl MerkleTree {
+    /// Build a tree over everything read from `reader`
+    pub fn from_reader<R: Read>(mut reader: R, block_size: u32) -> io::Result<Self> {
+        let mut builder = MerkleBuilder::new(block_size)?;
+        let mut buffer = vec![0u8; block_size as usize];
+        loop {
+            let n = reader.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            builder.update(&buffer[..n]);
+        }
+        Ok(builder.finish())
+    }
+
+    /// Build a tree over the file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P, block_size: u32) -> io::Result<Self> {
+        Self::from_reader(File::open(path)?, block_size)
+    }
+
+    /// Block size in bytes (the last block may be shorter)
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Length of the data the tree was built over
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Number of blocks (at least one, even for empty data)
+    pub fn block_count(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Root hash over all blocks
+    pub fn root(&self) -> &MerkleHash {
+        &self.root
+    }
+
+    /// Hash of block `index`
+    pub fn leaf(&self, index: u64) -> Option<&MerkleHash> {
+        self.leaves.get(usize::try_from(index).ok()?)
+    }
+
+    /// Byte range of block `index` within the data
+    pub fn block_range(&self, index: u64) -> Range<u64> {
+        let start = (index * self.block_size as u64).min(self.data_len);
+        let end = (start + self.block_size as u64).min(self.data_len);
+        start..end
+    }
+
+    /// Check `data` against the hash of block `index`
+    pub fn verify_block(&self, index: u64, data: &[u8]) -> bool {
+        self.leaf(index) == Some(&leaf_hash(data))
+    }
+
+    /// Read block `index` from `reader` and check it
+    ///
+    /// Returns `false` if the block is corrupt or cannot be read in full.
+    pub fn verify_block_at<R: Read + Seek>(&self, reader: &mut R, index: u64) -> io::Result<bool> {
+        if index >= self.block_count() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("block {index} out of range ({} blocks)", self.block_count()),
+            ));
+        }
+        let range = self.block_range(index);
+        let mut block = Vec::with_capacity((range.end - range.start) as usize);
+        reader.seek(SeekFrom::Start(range.start))?;
+        reader
+            .take(range.end - range.start)
+            .read_to_end(&mut block)?;
+        Ok(self.verify_block(index, &block))
+    }
+
+    /// Check every block of `reader` against the tree
+    pub fn verify_reader<R: Read>(&self, reader: R) -> io::Result<MerkleVerifyReport> {
+        let other = Self::from_reader(reader, self.block_size)?;
+        let corrupt_blocks = (0..self.block_count())
+            .filter(|&i| self.leaf(i) != other.leaf(i))
+            .collect();
+        Ok(MerkleVerifyReport {
+            blocks_checked: self.block_count(),
+            corrupt_blocks,
+            length_matches: other.data_len == self.data_len,
+        })
+    }
+
+    /// Encode the tree as a [`PayloadKind::MerkleTree`] envelope
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        wrap(
+            PayloadKind::MerkleTree,
+            BinaryWriteOptions::default(),
+            &to_bincode(self)?,
+        )
+    }
+
+    /// Decode a tree written by [`MerkleTree::to_bytes`]
+    ///
+    /// The stored root is checked against the block hashes, so a damaged
+    /// sidecar is rejected rather than reporting every block as corrupt.
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        let tree: Self = from_bincode(&unwrap_auto(PayloadKind::MerkleTree, data)?)?;
+        let expected_blocks = tree.data_len.div_ceil(tree.block_size.max(1) as u64).max(1);
+        if tree.block_size == 0
+            || tree.leaves.len() as u64 != expected_blocks
+            || root_hash(&tree.leaves) != tree.root
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "merkle tree is inconsistent",
+            ));
+        }
+        Ok(tree)
+    }
+
+    /// Write the tree to the sidecar of `path`, replacing it atomically
+    pub fn write_sidecar<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_atomic(merkle_sidecar_path(path), &self.to_bytes()?)
+    }
+
+    /// Read the tree from the sidecar of `path`
+    pub fn read_sidecar<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(merkle_sidecar_path(path))?)
+    }
+}
+
+/// Result of checking a file against its tree
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MerkleVerifyReport {
+    /// Blocks in the tree
+    pub blocks_checked: u64,
+    /// Indices of blocks whose hash does not match
+    pub corrupt_blocks: Vec<u64>,
+    /// Whether the file length matches the tree
+    pub length_matches: bool,
+}
+
+impl MerkleVerifyReport {
+    /// Whether every block matched and the length is unchanged
+    pub fn is_ok(&self) -> bool {
+        self.corrupt_blocks.is_empty() && self.length_matches
+    }
+}
+
+/// Incremental tree builder fed with consecutive data
+pub struct MerkleBuilder {
+    block_size: usize,
+    hasher: blake3::Hasher,
+    in_block: usize,
+    data_len: u64,
+    leaves: Vec<MerkleHash>,
+}
+
+impl MerkleBuilder {
+    /// Create a builder with the given block size
+    pub fn new(block_size: u32) -> io::Result<Self> {
+        if block_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "merkle block size must be non-zero",
+            ));
+        }
+        Ok(Self {
+            block_size: block_size as usize,
+            hasher: leaf_hasher(),
+            in_block: 0,
+            data_len: 0,
+            leaves: Vec::new(),
+        })
+    }
+
+    /// Feed the next bytes of data
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.data_len += data.len() as u64;
+        while !data.is_empty() {
+            let take = data.len().min(self.block_size - self.in_block);
+            self.hasher.update(&data[..take]);
+            self.in_block += take;
+            data = &data[take..];
+            if self.in_block == self.block_size {
+                self.leaves.push(*self.hasher.finalize().as_bytes());
+                self.hasher = leaf_hasher();
+                self.in_block = 0;
+            }
+        }
+    }
+
+    /// Finish the last block and build the tree
+    pub fn finish(mut self) -> MerkleTree {
+        if self.in_block > 0 || self.leaves.is_empty() {
+            self.leaves.push(*self.hasher.finalize().as_bytes());
+        }
+        MerkleTree {
+            block_size: self.block_size as u32,
+            data_len: self.data_len,
+            root: root_hash(&self.leaves),
+            leaves: self.leaves,
+        }
+    }
+}
+
+/// Writer adapter that builds a tree over everything written through it
+pub struct MerkleWriter<W> {
+    inner: W,
+    builder: MerkleBuilder,
+}
+
+impl<W: Write> MerkleWriter<W> {
+    /// Wrap `inner`, hashing in blocks of `block_size`
+    pub fn new(inner: W, block_size: u32) -> io::Result<Self> {
+        Ok(Self {
+            inner,
+            builder: MerkleBuilder::new(block_size)?,
+        })
+    }
+
+    /// Flush and return the inner writer with the finished tree
+    pub fn finish(mut self) -> io::Result<(W, MerkleTree)> {
+        self.inner.flush()?;
+        Ok((self.inner, self.builder.finish()))
+    }
+}
+
+impl<W: Write> Write for MerkleWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.builder.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Sidecar path holding the tree for `path` (`<path>.merkle`)
+pub fn merkle_sidecar_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".merkle");
+    PathBuf::from(name)
+}
+
+/// Check a file against its sidecar tree
+pub fn verify_merkle_file<P: AsRef<Path>>(path: P) -> io::Result<MerkleVerifyReport> {
+    let tree = MerkleTree::read_sidecar(&path)?;
+    tree.verify_reader(File::open(path)?)
+}
+
+/// Check a single block of a file against its sidecar tree
+pub fn verify_merkle_block<P: AsRef<Path>>(path: P, index: u64) -> io::Result<bool> {
+    let tree = MerkleTree::read_sidecar(&path)?;
+    tree.verify_block_at(&mut File::open(path)?, index)
+}
+
+/// Write data to a file in streaming fashion and store its tree in a sidecar
+///
+/// Like [`stream_write_file`](super::stream::stream_write_file), hashing the
+/// data as it is written.
+pub fn stream_write_file_with_merkle<P, I, D>(
+    path: P,
+    chunks: I,
+    block_size: u32,
+) -> io::Result<MerkleTree>
+where
+    P: AsRef<Path>,
+    I: Iterator<Item = D>,
+    D: AsRef<[u8]>,
+{
+    let file = AtomicFile::create(&path)?;
+    let mut writer = StreamWriter::new(MerkleWriter::new(file, block_size)?);
+
+    for chunk in chunks {
+        writer.write_chunk(chunk.as_ref())?;
+    }
+
+    let (file, tree) = writer.finish()?.finish()?;
+    file.commit()?;
+    tree.write_sidecar(&path)?;
+    Ok(tree)
+}
+
+/// Stream-compress a file and store a tree over the compressed output
+///
+/// Like [`compress_file`](super::stream_compress::compress_file); returns
+/// (uncompressed_size, compressed_size, tree).
+pub fn compress_file_with_merkle<P: AsRef<Path>, Q: AsRef<Path>>(
+    input_path: P,
+    output_path: Q,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    block_size: u32,
+) -> io::Result<(u64, u64, MerkleTree)> {
+    let input = File::open(input_path)?;
+    let input_size = input.metadata()?.len();
+    let mut output = MerkleWriter::new(AtomicFile::create(output_path.as_ref())?, block_size)?;
+
+    stream_compress(input, &mut output, codec, level, 64 * 1024)?;
+
+    let (file, tree) = output.finish()?;
+    file.commit()?;
+    tree.write_sidecar(&output_path)?;
+    Ok((input_size, tree.data_len(), tree))
+}
+
+fn leaf_hasher() -> blake3::Hasher {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher
+}
+
+fn leaf_hash(data: &[u8]) -> MerkleHash {
+    *leaf_hasher().update(data).finalize().as_bytes()
+}
+
+fn root_hash(leaves: &[MerkleHash]) -> MerkleHash {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = blake3::Hasher::new();
+                    hasher.update(&[NODE_PREFIX]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    *hasher.finalize().as_bytes()
+                }
+                [single] => *single,
+                _ => unreachable!("chunks(2) yields one or two items"),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_builder_matches_from_reader() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let tree = MerkleTree::from_reader(Cursor::new(&data), 1024).unwrap();
+        assert_eq!(tree.block_count(), 10);
+        assert_eq!(tree.block_range(9), 9216..10_000);
+
+        // Feeding in odd-sized pieces gives the same tree
+        let mut builder = MerkleBuilder::new(1024).unwrap();
+        for piece in data.chunks(333) {
+            builder.update(piece);
+        }
+        assert_eq!(builder.finish(), tree);
+
+        let empty = MerkleTree::from_reader(Cursor::new(b""), 1024).unwrap();
+        assert_eq!(empty.block_count(), 1);
+        assert!(empty.verify_block(0, b""));
+    }
+
+    #[test]
+    fn test_sidecar_roundtrip_and_block_checks() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let data = vec![7u8; 5000];
+        let tree = stream_write_file_with_merkle(&path, data.chunks(700), 1024).unwrap();
+
+        assert_eq!(MerkleTree::read_sidecar(&path).unwrap(), tree);
+        assert!(verify_merkle_file(&path).unwrap().is_ok());
+        assert!(verify_merkle_block(&path, 4).unwrap());
+        assert!(verify_merkle_block(&path, 5).is_err());
+    }
+
+    #[test]
+    fn test_pinpoints_corrupt_block() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let data: Vec<u8> = (0..8192u32).map(|i| (i * 7) as u8).collect();
+        stream_write_file_with_merkle(&path, std::iter::once(&data), 1024).unwrap();
+
+        let mut corrupted = data.clone();
+        corrupted[3 * 1024 + 10] ^= 0xFF;
+        fs::write(&path, &corrupted).unwrap();
+
+        let report = verify_merkle_file(&path).unwrap();
+        assert_eq!(report.corrupt_blocks, vec![3]);
+        assert!(report.length_matches);
+        assert!(!verify_merkle_block(&path, 3).unwrap());
+        assert!(verify_merkle_block(&path, 2).unwrap());
+    }
+
+    #[test]
+    fn test_damaged_sidecar_rejected() {
+        let tree = MerkleTree::from_reader(Cursor::new(vec![1u8; 3000]), 1024).unwrap();
+        let mut bytes = tree.to_bytes().unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        assert!(MerkleTree::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_compress_file_with_merkle_covers_output() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("in.bin");
+        let output = dir.path().join("out.bin");
+        fs::write(&input, vec![3u8; 4096]).unwrap();
+
+        let (raw, stored, tree) = compress_file_with_merkle(
+            &input,
+            &output,
+            CompressionCodec::None,
+            CompressionLevel::Default,
+            512,
+        )
+        .unwrap();
+        assert_eq!(raw, 4096);
+        assert_eq!(stored, fs::metadata(&output).unwrap().len());
+        assert_eq!(tree.data_len(), stored);
+        assert!(verify_merkle_file(&output).unwrap().is_ok());
+    }
+}
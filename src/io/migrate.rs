@@ -0,0 +1,243 @@
+//! Codec migration for stored envelopes
+//!
+//! Re-encodes every envelope in a directory tree with new write options,
+//! e.g. when the default codec changes from LZ4 to zstd. Files are replaced
+//! atomically, keep their payload kind, and files already using the target
+//! codec are left untouched.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{rewrap_dir, BinaryWriteOptions, CompressionCodec, RewrapOptions};
+//!
+//! let opts = RewrapOptions::new(BinaryWriteOptions {
+//!     codec: CompressionCodec::Zstd,
+//!     level: Some(9),
+//! });
+//! let report = rewrap_dir("engrams/", &opts).unwrap();
+//! println!(
+//!     "rewrote {} files, saved {} bytes",
+//!     report.rewritten,
+//!     report.bytes_saved()
+//! );
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::atomic::write_atomic;
+use super::envelope::{rewrap, BinaryWriteOptions, CompressionCodec, EnvelopeHeader, PayloadKind};
+
+/// Options for [`rewrap_dir`]
+#[derive(Clone, Copy, Debug)]
+pub struct RewrapOptions {
+    /// Target codec and level
+    pub write: BinaryWriteOptions,
+    /// Payload kind assigned to legacy (headerless) files
+    pub legacy_kind: PayloadKind,
+    /// Convert legacy raw files into envelopes
+    pub convert_legacy: bool,
+    /// Descend into subdirectories
+    pub recursive: bool,
+}
+
+impl RewrapOptions {
+    /// Create options targeting `write`, converting legacy files as engrams
+    pub fn new(write: BinaryWriteOptions) -> Self {
+        Self {
+            write,
+            legacy_kind: PayloadKind::EngramBincode,
+            convert_legacy: true,
+            recursive: true,
+        }
+    }
+}
+
+/// Summary of a [`rewrap_dir`] run
+#[derive(Clone, Debug, Default)]
+pub struct RewrapReport {
+    /// Regular files examined
+    pub files_scanned: u64,
+    /// Files re-encoded and replaced
+    pub rewritten: u64,
+    /// Files left untouched (already on the target codec, or legacy when not converting)
+    pub skipped: u64,
+    /// Files that could not be re-encoded, with the error message
+    pub failed: Vec<(PathBuf, String)>,
+    /// Total size of rewritten files before re-encoding
+    pub bytes_before: u64,
+    /// Total size of rewritten files after re-encoding
+    pub bytes_after: u64,
+}
+
+impl RewrapReport {
+    /// Bytes saved by re-encoding (negative if the files grew)
+    pub fn bytes_saved(&self) -> i64 {
+        self.bytes_before as i64 - self.bytes_after as i64
+    }
+}
+
+/// Outcome of re-encoding a single file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewrapOutcome {
+    /// File was replaced; sizes before and after
+    Rewritten { before: u64, after: u64 },
+    /// File already matched the target and was left untouched
+    Skipped,
+}
+
+/// Re-encode a single envelope file in place
+///
+/// The file is replaced atomically so a crash never leaves a truncated envelope.
+pub fn rewrap_file<P: AsRef<Path>>(path: P, opts: &RewrapOptions) -> io::Result<RewrapOutcome> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+
+    match EnvelopeHeader::parse(&data)? {
+        Some(header) if header.codec == opts.write.codec => return Ok(RewrapOutcome::Skipped),
+        None if !opts.convert_legacy || opts.write.codec == CompressionCodec::None => {
+            return Ok(RewrapOutcome::Skipped)
+        }
+        _ => {}
+    }
+
+    let rewrapped = rewrap(&data, opts.write, opts.legacy_kind)?;
+    write_atomic(path, &rewrapped)?;
+
+    Ok(RewrapOutcome::Rewritten {
+        before: data.len() as u64,
+        after: rewrapped.len() as u64,
+    })
+}
+
+/// Re-encode every envelope file under `dir`
+///
+/// Failures on individual files are recorded in the report and do not stop
+/// the run; errors walking the directory itself are returned.
+pub fn rewrap_dir<P: AsRef<Path>>(dir: P, opts: &RewrapOptions) -> io::Result<RewrapReport> {
+    let mut report = RewrapReport::default();
+    rewrap_dir_into(dir.as_ref(), opts, &mut report)?;
+    Ok(report)
+}
+
+fn rewrap_dir_into(dir: &Path, opts: &RewrapOptions, report: &mut RewrapReport) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if opts.recursive {
+                rewrap_dir_into(&path, opts, report)?;
+            }
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+
+        report.files_scanned += 1;
+        match rewrap_file(&path, opts) {
+            Ok(RewrapOutcome::Rewritten { before, after }) => {
+                report.rewritten += 1;
+                report.bytes_before += before;
+                report.bytes_after += after;
+            }
+            Ok(RewrapOutcome::Skipped) => report.skipped += 1,
+            Err(e) => report.failed.push((path, e.to_string())),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unwrap_auto, wrap_or_legacy};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rewrap_dir_skips_target_codec() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"legacy payload").unwrap();
+
+        // Target codec None: legacy files already match and are skipped
+        let opts = RewrapOptions::new(BinaryWriteOptions::default());
+        let report = rewrap_dir(dir.path(), &opts).unwrap();
+
+        assert_eq!(report.files_scanned, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.rewritten, 0);
+        assert_eq!(report.bytes_saved(), 0);
+    }
+
+    #[test]
+    fn test_rewrap_dir_records_failures() {
+        let dir = tempdir().unwrap();
+        // Envelope header with an unknown payload kind
+        let mut bad = b"EDN1".to_vec();
+        bad.extend_from_slice(&[0xEE, 0, 0, 0]);
+        bad.extend_from_slice(&0u64.to_le_bytes());
+        fs::write(dir.path().join("bad.bin"), &bad).unwrap();
+
+        let opts = RewrapOptions::new(BinaryWriteOptions::default());
+        let report = rewrap_dir(dir.path(), &opts).unwrap();
+
+        assert_eq!(report.failed.len(), 1);
+        // The broken file is left as it was
+        assert_eq!(fs::read(dir.path().join("bad.bin")).unwrap(), bad);
+    }
+
+    #[cfg(all(feature = "compression-zstd", feature = "compression-lz4"))]
+    #[test]
+    fn test_rewrap_dir_migrates_codec() {
+        let dir = tempdir().unwrap();
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+
+        let data = b"engram data that compresses nicely ".repeat(200);
+        let lz4 = BinaryWriteOptions {
+            codec: CompressionCodec::Lz4,
+            level: None,
+        };
+        let zstd = BinaryWriteOptions {
+            codec: CompressionCodec::Zstd,
+            level: Some(19),
+        };
+
+        let sub = wrap_or_legacy(PayloadKind::SubEngramBincode, lz4, &data).unwrap();
+        fs::write(nested.join("sub.bin"), &sub).unwrap();
+        let done = wrap_or_legacy(PayloadKind::EngramBincode, zstd, &data).unwrap();
+        fs::write(dir.path().join("done.bin"), &done).unwrap();
+        fs::write(dir.path().join("legacy.bin"), &data).unwrap();
+
+        let report = rewrap_dir(dir.path(), &RewrapOptions::new(zstd)).unwrap();
+        assert_eq!(report.files_scanned, 3);
+        assert_eq!(report.rewritten, 2);
+        assert_eq!(report.skipped, 1);
+        assert!(report.failed.is_empty());
+        assert!(report.bytes_saved() > 0);
+
+        // Kind is preserved and the codec is now zstd
+        let sub = fs::read(nested.join("sub.bin")).unwrap();
+        let header = EnvelopeHeader::parse(&sub).unwrap().unwrap();
+        assert_eq!(header.kind, PayloadKind::SubEngramBincode);
+        assert_eq!(header.codec, CompressionCodec::Zstd);
+        assert_eq!(
+            unwrap_auto(PayloadKind::SubEngramBincode, &sub).unwrap(),
+            data
+        );
+
+        // Legacy file became an engram envelope
+        let legacy = fs::read(dir.path().join("legacy.bin")).unwrap();
+        let header = EnvelopeHeader::parse(&legacy).unwrap().unwrap();
+        assert_eq!(header.kind, PayloadKind::EngramBincode);
+        assert_eq!(
+            unwrap_auto(PayloadKind::EngramBincode, &legacy).unwrap(),
+            data
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Memory-mapped read path for large files
+//!
+//! [`MappedFile`] maps a file read-only so envelope header parsing, bincode
+//! decoding and chunk iteration run directly over the page cache instead of
+//! copying the file into a heap buffer first.
+//!
+//! # Safety
+//!
+//! As with [`memmap2::Mmap::map`], the slices handed out by a mapping are only
+//! sound while no one modifies or truncates the underlying file. Writes made
+//! in place show up in slices that are already borrowed (so a `&str` decoded
+//! from the mapping could stop being valid UTF-8), and touching a page past
+//! the end of a truncated file raises `SIGBUS`. Nothing in this module can
+//! prevent either, so creating a mapping is `unsafe` and the caller promises
+//! the file stays unchanged for as long as the mapping or anything borrowed
+//! from it is alive.
+//!
+//! Files replaced through the atomic writers in this crate satisfy this: the
+//! rename leaves the mapped inode untouched. [`MappedFile::check`] can detect
+//! some violations (a file that has already shrunk) but is a diagnostic only.
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{MappedFile, PayloadKind};
+//!
+//! // SAFETY: nothing modifies engram.bin while it is mapped
+//! let mapped = unsafe { MappedFile::open("engram.bin") }.unwrap();
+//! // Borrowed straight from the mapping when the envelope is uncompressed
+//! let payload = mapped.unwrap_auto(PayloadKind::EngramBincode).unwrap();
+//!
+//! let mut total = 0;
+//! for chunk in mapped.chunks(1 << 20) {
+//!     total += chunk.unwrap().len();
+//! }
+//! assert_eq!(total as u64, mapped.len());
+//! # let _ = payload;
+//! ```
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::bincode_config::BincodeConfig;
+use super::cdc::CdcConfig;
+use super::envelope::{unwrap_borrowed, unwrap_cow, EnvelopeHeader, PayloadKind};
+
+/// Read-only memory mapping of a file
+pub struct MappedFile {
+    file: File,
+    // Zero-length files are not mapped
+    map: Option<Mmap>,
+}
+
+impl MappedFile {
+    /// Map the file at `path`
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the returned mapping,
+    /// or any slice borrowed from it, is alive. See the [module docs](self).
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        // SAFETY: forwarded to the caller
+        unsafe { Self::from_file(File::open(path)?) }
+    }
+
+    /// Map an already opened file
+    ///
+    /// # Safety
+    ///
+    /// Same contract as [`MappedFile::open`].
+    pub unsafe fn from_file(file: File) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        let map = if len == 0 {
+            None
+        } else {
+            // SAFETY: the caller guarantees the file is not modified while mapped
+            Some(unsafe { Mmap::map(&file)? })
+        };
+        Ok(Self { file, map })
+    }
+
+    /// Mapped length in bytes
+    pub fn len(&self) -> u64 {
+        self.map.as_ref().map_or(0, |m| m.len() as u64)
+    }
+
+    /// Whether the mapped file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fail with `UnexpectedEof` if the file is now shorter than the mapping
+    ///
+    /// Best-effort diagnostic for callers that broke the mapping contract: it
+    /// cannot see in-place writes, and a truncation can still happen right
+    /// after it returns. It does not make slices safe to hold.
+    pub fn check(&self) -> io::Result<()> {
+        if self.file.metadata()?.len() < self.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file truncated while mapped",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The whole mapping, after a best-effort [`check`](Self::check)
+    pub fn as_slice(&self) -> io::Result<&[u8]> {
+        self.check()?;
+        Ok(self.map.as_deref().unwrap_or(&[]))
+    }
+
+    /// Bytes in `range`, after checking bounds and the file length
+    pub fn slice(&self, range: Range<usize>) -> io::Result<&[u8]> {
+        self.as_slice()?
+            .get(range)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "range outside mapped file"))
+    }
+
+    /// Parse the envelope header at the start of the file
+    ///
+    /// Returns `Ok(None)` for legacy data, like [`EnvelopeHeader::parse`].
+    pub fn envelope_header(&self) -> io::Result<Option<EnvelopeHeader>> {
+        EnvelopeHeader::parse(self.as_slice()?)
+    }
+
+    /// Unwrap the file contents like [`unwrap_auto`](super::unwrap_auto)
+    ///
+    /// Uncompressed envelopes and legacy data are borrowed from the mapping;
+    /// only compressed payloads are decoded into a new buffer.
+    pub fn unwrap_auto(&self, expected_kind: PayloadKind) -> io::Result<Cow<'_, [u8]>> {
+        unwrap_cow(expected_kind, self.as_slice()?)
+    }
+
+    /// Unwrap the file contents like [`unwrap_borrowed`](super::unwrap_borrowed)
+    ///
+    /// The payload is always borrowed from the mapping; compressed envelopes
+    /// are rejected.
+    pub fn unwrap_borrowed(&self, expected_kind: PayloadKind) -> io::Result<&[u8]> {
+        unwrap_borrowed(expected_kind, self.as_slice()?)
+    }
+
+    /// Iterate over fixed-size chunks of the mapping
+    pub fn chunks(&self, chunk_size: usize) -> MappedChunks<'_> {
+        MappedChunks {
+            file: self,
+            pos: 0,
+            mode: ChunkMode::Fixed(chunk_size.max(1)),
+        }
+    }
+
+    /// Iterate over content-defined chunks of the mapping
+    ///
+    /// Boundaries match [`ChunkStream::content_defined`](super::ChunkStream::content_defined)
+    /// over the same data.
+    pub fn chunks_cdc(&self, config: CdcConfig) -> MappedChunks<'_> {
+        MappedChunks {
+            file: self,
+            pos: 0,
+            mode: ChunkMode::ContentDefined(config),
+        }
+    }
+}
+
+enum ChunkMode {
+    Fixed(usize),
+    ContentDefined(CdcConfig),
+}
+
+/// Iterator over chunks of a [`MappedFile`], borrowing from the mapping
+///
+/// The file length is checked (best-effort) before each chunk; iteration
+/// stops after the first error.
+pub struct MappedChunks<'a> {
+    file: &'a MappedFile,
+    pos: usize,
+    mode: ChunkMode,
+}
+
+impl<'a> Iterator for MappedChunks<'a> {
+    type Item = io::Result<&'a [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos as u64 >= self.file.len() {
+            return None;
+        }
+        let data = match self.file.as_slice() {
+            Ok(data) => data,
+            Err(e) => {
+                self.pos = usize::MAX;
+                return Some(Err(e));
+            }
+        };
+        let rest = &data[self.pos..];
+        let len = match &self.mode {
+            ChunkMode::Fixed(size) => rest.len().min(*size),
+            ChunkMode::ContentDefined(config) => config.cut_point(rest),
+        };
+        self.pos += len;
+        Some(Ok(&rest[..len]))
+    }
+}
+
+/// Read and deserialize a bincode file through a memory mapping
+///
+/// # Safety
+///
+/// The file must not be modified or truncated until this returns.
+pub unsafe fn read_bincode_mmap<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+    path: P,
+) -> io::Result<T> {
+    // SAFETY: forwarded to the caller; the mapping is dropped before returning
+    let mapped = unsafe { MappedFile::open(path)? };
+    super::from_bincode(mapped.as_slice()?)
+}
+
+/// Read and deserialize a bincode file through a memory mapping with explicit options
+///
+/// # Safety
+///
+/// The file must not be modified or truncated until this returns.
+pub unsafe fn read_bincode_mmap_with_config<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+    path: P,
+    config: BincodeConfig,
+) -> io::Result<T> {
+    // SAFETY: forwarded to the caller; the mapping is dropped before returning
+    let mapped = unsafe { MappedFile::open(path)? };
+    super::from_bincode_with_config(mapped.as_slice()?, config)
+}
+
+/// Read a file in chunks through a memory mapping, without copying each chunk
+///
+/// # Safety
+///
+/// The file must not be modified or truncated until this returns.
+pub unsafe fn read_chunks_mmap<P, F>(path: P, chunk_size: usize, mut callback: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&[u8]) -> io::Result<()>,
+{
+    // SAFETY: forwarded to the caller; the mapping is dropped before returning
+    let mapped = unsafe { MappedFile::open(path)? };
+    for chunk in mapped.chunks(chunk_size) {
+        callback(chunk?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{wrap, BinaryWriteOptions, ChunkStream, CompressionCodec};
+    use std::fs::OpenOptions;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    // SAFETY (all tests): each file lives in a private temp dir and is only
+    // modified while mapped in `test_truncated_file_is_an_error`, which never
+    // touches the mapping again after the truncation.
+
+    #[test]
+    fn test_unwrap_borrows_uncompressed_payload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("engram.bin");
+        let opts = BinaryWriteOptions {
+            codec: CompressionCodec::None,
+            level: None,
+        };
+        std::fs::write(
+            &path,
+            wrap(PayloadKind::EngramBincode, opts, b"payload").unwrap(),
+        )
+        .unwrap();
+
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        let header = mapped.envelope_header().unwrap().unwrap();
+        assert_eq!(header.uncompressed_len, 7);
+
+        let payload = mapped.unwrap_auto(PayloadKind::EngramBincode).unwrap();
+        assert!(matches!(payload, Cow::Borrowed(b"payload")));
+    }
+
+    #[test]
+    fn test_borrowed_decode_points_into_mapping() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Entry<'a> {
+            id: u32,
+            name: &'a str,
+        }
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index.bin");
+        let raw = crate::io::to_bincode(&Entry {
+            id: 9,
+            name: "engram-index",
+        })
+        .unwrap();
+        let opts = BinaryWriteOptions {
+            codec: CompressionCodec::None,
+            level: None,
+        };
+        std::fs::write(
+            &path,
+            wrap(PayloadKind::SubEngramBincode, opts, &raw).unwrap(),
+        )
+        .unwrap();
+
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        let payload = mapped
+            .unwrap_borrowed(PayloadKind::SubEngramBincode)
+            .unwrap();
+        let entry: Entry = crate::io::from_bincode_borrowed(payload).unwrap();
+        assert_eq!((entry.id, entry.name), (9, "engram-index"));
+        let mapping = mapped.as_slice().unwrap().as_ptr_range();
+        assert!(mapping.contains(&entry.name.as_ptr()));
+    }
+
+    #[test]
+    fn test_chunks_match_chunk_stream() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        std::fs::write(&path, &data).unwrap();
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+
+        let fixed: Vec<usize> = mapped.chunks(4096).map(|c| c.unwrap().len()).collect();
+        assert_eq!(fixed.len(), 25);
+        assert_eq!(fixed.iter().sum::<usize>(), data.len());
+
+        let config = CdcConfig::new(1024, 4096, 16 * 1024).unwrap();
+        let mapped_cdc: Vec<Vec<u8>> = mapped
+            .chunks_cdc(config)
+            .map(|c| c.unwrap().to_vec())
+            .collect();
+        let mut streamed = Vec::new();
+        ChunkStream::content_defined(Cursor::new(&data), config)
+            .process_all(|chunk| {
+                streamed.push(chunk.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(mapped_cdc, streamed);
+    }
+
+    #[test]
+    fn test_truncated_file_is_an_error() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("shrinking.bin");
+        std::fs::write(&path, vec![7u8; 8192]).unwrap();
+
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        let mut chunks = mapped.chunks(4096);
+        assert_eq!(chunks.next().unwrap().unwrap().len(), 4096);
+
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(100)
+            .unwrap();
+
+        let err = chunks.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(chunks.next().is_none());
+        assert!(mapped.as_slice().is_err());
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("empty.bin");
+        std::fs::write(&path, b"").unwrap();
+
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        assert!(mapped.is_empty());
+        assert_eq!(mapped.chunks(16).count(), 0);
+        assert!(mapped.envelope_header().unwrap().is_none());
+    }
+}
@@ -1,13 +1,57 @@
+pub mod archive;
+pub mod atomic;
+pub mod bincode_config;
 pub mod buffer;
+pub mod cdc;
+#[cfg(feature = "chunk-store")]
+pub mod chunk_store;
+pub mod durability;
 pub mod envelope;
+pub mod format;
+pub mod framed;
+#[cfg(all(unix, feature = "fs-image"))]
+pub mod fs_image;
+pub mod hashing;
+pub mod jsonl;
+#[cfg(feature = "merkle")]
+pub mod merkle;
+pub mod migrate;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod pool;
 pub mod profiles;
+pub mod progress;
+pub mod rate_limit;
 pub mod serialize;
 pub mod stream;
 pub mod stream_compress;
+pub mod versioned;
 
+pub use archive::*;
+pub use atomic::*;
+pub use bincode_config::*;
 pub use buffer::*;
+pub use cdc::*;
+#[cfg(feature = "chunk-store")]
+pub use chunk_store::*;
+pub use durability::*;
 pub use envelope::*;
+pub use format::*;
+pub use framed::*;
+#[cfg(all(unix, feature = "fs-image"))]
+pub use fs_image::*;
+pub use hashing::*;
+pub use jsonl::*;
+#[cfg(feature = "merkle")]
+pub use merkle::*;
+pub use migrate::*;
+#[cfg(feature = "mmap")]
+pub use mmap::*;
+pub use pool::*;
 pub use profiles::*;
+pub use progress::*;
+pub use rate_limit::*;
 pub use serialize::*;
 pub use stream::*;
 pub use stream_compress::*;
+pub use versioned::*;
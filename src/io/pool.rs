@@ -0,0 +1,209 @@
+//! Reusable buffer pool for chunked reads
+//!
+//! Chunked readers normally allocate a fresh `Vec` for every chunk. A
+//! [`BufferPool`] hands out [`PooledBuffer`]s instead, which go back to the
+//! pool when dropped, so a long-running chunking loop settles on a small set
+//! of buffers rather than churning the allocator.
+//!
+//! `ChunkStream`, `StreamReader` and `AsyncStreamReader` accept a pool via
+//! `with_pool`.
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{BufferPool, ChunkStream};
+//! use std::io::Cursor;
+//!
+//! let pool = BufferPool::new(4096);
+//! let mut stream = ChunkStream::with_chunk_size(Cursor::new(vec![1u8; 10_000]), 4096)
+//!     .with_pool(pool.clone());
+//!
+//! let mut total = 0;
+//! while let Some(chunk) = stream.next_pooled().unwrap() {
+//!     total += chunk.len();
+//!     // `chunk` returns to the pool here
+//! }
+//! assert_eq!(total, 10_000);
+//! assert_eq!(pool.available(), 1);
+//! ```
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// Default number of idle buffers a pool keeps
+pub const DEFAULT_POOL_CAPACITY: usize = 64;
+
+/// Shared pool of reusable byte buffers
+///
+/// Cloning a pool yields another handle to the same buffers.
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    buffer_size: usize,
+    max_idle: usize,
+}
+
+impl BufferPool {
+    /// Create a pool of buffers with at least `buffer_size` bytes of capacity
+    pub fn new(buffer_size: usize) -> Self {
+        Self::with_capacity(buffer_size, DEFAULT_POOL_CAPACITY)
+    }
+
+    /// Create a pool that keeps at most `max_idle` buffers around
+    pub fn with_capacity(buffer_size: usize, max_idle: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                buffers: Mutex::new(Vec::new()),
+                buffer_size,
+                max_idle,
+            }),
+        }
+    }
+
+    /// Capacity of the buffers handed out by this pool
+    pub fn buffer_size(&self) -> usize {
+        self.inner.buffer_size
+    }
+
+    /// Number of idle buffers waiting in the pool
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Take an empty buffer from the pool, allocating one if none is idle
+    pub fn get(&self) -> PooledBuffer {
+        let buf = self
+            .lock()
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(self.inner.buffer_size));
+        PooledBuffer {
+            buf,
+            pool: self.clone(),
+        }
+    }
+
+    fn put(&self, mut buf: Vec<u8>) {
+        // Buffers that were shrunk or replaced are not worth keeping
+        if buf.capacity() < self.inner.buffer_size {
+            return;
+        }
+        let mut buffers = self.lock();
+        if buffers.len() < self.inner.max_idle {
+            buf.clear();
+            buffers.push(buf);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        // The pool holds no invariants a panicking holder could break
+        self.inner
+            .buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("buffer_size", &self.inner.buffer_size)
+            .field("max_idle", &self.inner.max_idle)
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+/// Buffer borrowed from a [`BufferPool`], returned to it on drop
+pub struct PooledBuffer {
+    buf: Vec<u8>,
+    pool: BufferPool,
+}
+
+impl PooledBuffer {
+    /// Detach the buffer from the pool and keep it
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+
+    /// Fill the buffer from `reader` with up to `len` bytes, returning the count read
+    pub(crate) fn read_from<R: std::io::Read>(
+        &mut self,
+        reader: &mut R,
+        len: usize,
+    ) -> std::io::Result<usize> {
+        self.buf.resize(len, 0);
+        let n = reader.read(&mut self.buf)?;
+        self.buf.truncate(n);
+        Ok(n)
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledBuffer {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledBuffer")
+            .field("len", &self.buf.len())
+            .finish()
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        self.pool.put(std::mem::take(&mut self.buf));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let pool = BufferPool::new(1024);
+        let mut first = pool.get();
+        first.extend_from_slice(b"data");
+        let ptr = first.as_ptr();
+        drop(first);
+        assert_eq!(pool.available(), 1);
+
+        let second = pool.get();
+        assert!(second.is_empty());
+        assert!(second.capacity() >= 1024);
+        assert_eq!(second.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_idle_buffers_are_capped() {
+        let pool = BufferPool::with_capacity(16, 2);
+        let held: Vec<_> = (0..4).map(|_| pool.get()).collect();
+        drop(held);
+        assert_eq!(pool.available(), 2);
+
+        let detached = pool.get().into_vec();
+        assert!(detached.capacity() >= 16);
+        assert_eq!(pool.available(), 1);
+    }
+}
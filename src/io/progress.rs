@@ -0,0 +1,226 @@
+//! Progress reporting and cancellation for long-running I/O
+//!
+//! The `_with_hooks` variants of the file and stream helpers take an
+//! [`IoHooks`] carrying an optional [`Progress`] observer, an optional
+//! [`CancellationToken`] and an optional [`RateLimiter`]. The token is checked
+//! before each chunk is read; a cancelled operation stops with an error
+//! recognised by [`is_cancelled`].
+//!
+//! # Examples
+//! ```no_run
+//! use embeddenator_io::{compress_file_with_hooks, is_cancelled, CancellationToken};
+//! use embeddenator_io::{CompressionCodec, CompressionLevel, IoHooks};
+//!
+//! let token = CancellationToken::new();
+//! let hooks = IoHooks::new()
+//!     .with_progress(|done: u64, total: Option<u64>| {
+//!         println!("{done} / {}", total.unwrap_or(0));
+//!     })
+//!     .with_cancellation(token.clone());
+//!
+//! // e.g. the UI calls token.cancel() from another thread
+//! match compress_file_with_hooks("in.bin", "out.zst", CompressionCodec::Zstd, CompressionLevel::Default, &hooks) {
+//!     Err(e) if is_cancelled(&e) => println!("cancelled"),
+//!     other => println!("{:?}", other),
+//! }
+//! ```
+
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::rate_limit::RateLimiter;
+
+/// Observer notified as an operation makes progress
+pub trait Progress: Send + Sync {
+    /// Called after each chunk with the bytes processed so far and the total, if known
+    fn on_progress(&self, processed: u64, total: Option<u64>);
+}
+
+impl<F: Fn(u64, Option<u64>) + Send + Sync> Progress for F {
+    fn on_progress(&self, processed: u64, total: Option<u64>) {
+        self(processed, total)
+    }
+}
+
+/// Shared flag used to request cancellation of running operations
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of every operation holding a clone of this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Error payload for cancelled operations
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether `err` was produced by a cancelled operation
+pub fn is_cancelled(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<Cancelled>())
+}
+
+/// Optional progress observer, cancellation token and rate limit for an operation
+#[derive(Clone, Default)]
+pub struct IoHooks {
+    progress: Option<Arc<dyn Progress>>,
+    cancel: Option<CancellationToken>,
+    rate_limit: Option<RateLimiter>,
+}
+
+impl IoHooks {
+    /// Hooks with no observer and no token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report progress to `progress`
+    pub fn with_progress<P: Progress + 'static>(mut self, progress: P) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Stop when `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Throttle each chunk through `limiter`
+    pub fn with_rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limit = Some(limiter);
+        self
+    }
+
+    /// Charge `bytes` to the rate limiter, if any, sleeping the current thread as needed
+    pub fn throttle(&self, bytes: u64) {
+        if let Some(limiter) = &self.rate_limit {
+            limiter.acquire(bytes);
+        }
+    }
+
+    /// Charge `bytes` to the rate limiter, if any, sleeping the current task as needed
+    #[cfg(feature = "async")]
+    pub async fn throttle_async(&self, bytes: u64) {
+        if let Some(limiter) = &self.rate_limit {
+            limiter.acquire_async(bytes).await;
+        }
+    }
+
+    /// Return the cancellation error if cancellation was requested
+    pub fn check(&self) -> io::Result<()> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => Err(io::Error::other(Cancelled)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Notify the observer, if any
+    pub fn report(&self, processed: u64, total: Option<u64>) {
+        if let Some(progress) = &self.progress {
+            progress.on_progress(processed, total);
+        }
+    }
+}
+
+impl fmt::Debug for IoHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IoHooks")
+            .field("progress", &self.progress.is_some())
+            .field("cancel", &self.cancel)
+            .field("rate_limit", &self.rate_limit.is_some())
+            .finish()
+    }
+}
+
+/// Reader adapter that applies [`IoHooks`] on every read
+pub(crate) struct HookedReader<'a, R> {
+    inner: R,
+    hooks: &'a IoHooks,
+    processed: u64,
+    total: Option<u64>,
+}
+
+impl<'a, R: Read> HookedReader<'a, R> {
+    pub(crate) fn new(inner: R, hooks: &'a IoHooks, total: Option<u64>) -> Self {
+        Self {
+            inner,
+            hooks,
+            processed: 0,
+            total,
+        }
+    }
+}
+
+impl<R: Read> Read for HookedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.hooks.check()?;
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.processed += n as u64;
+            self.hooks.throttle(n as u64);
+            self.hooks.report(self.processed, self.total);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_cancelled_error_is_distinct() {
+        let token = CancellationToken::new();
+        let hooks = IoHooks::new().with_cancellation(token.clone());
+        assert!(hooks.check().is_ok());
+
+        token.cancel();
+        let err = hooks.check().unwrap_err();
+        assert!(is_cancelled(&err));
+        assert_ne!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(!is_cancelled(&io::Error::other("other failure")));
+    }
+
+    #[test]
+    fn test_hooked_reader_reports_progress() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let hooks = IoHooks::new().with_progress(move |done: u64, total: Option<u64>| {
+            sink.lock().unwrap().push((done, total));
+        });
+
+        let data = [0u8; 10];
+        let mut reader = HookedReader::new(&data[..], &hooks, Some(10));
+        let mut buf = [0u8; 4];
+        while reader.read(&mut buf).unwrap() > 0 {}
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(4, Some(10)), (8, Some(10)), (10, Some(10))]
+        );
+    }
+}
@@ -3,12 +3,23 @@
 //! Provides high-level interfaces for encoding/decoding data in various formats:
 //! - Bincode (binary, efficient)
 //! - JSON (text, human-readable)
+//! - MessagePack (binary, for exchange with non-Rust tools; `format-msgpack`)
+//! - CBOR (binary, for exchange with non-Rust tools; `format-cbor`)
+//! - postcard (compact, embedded-friendly; `format-postcard`)
 //!
 //! Both sync and async variants are available when the `async` feature is enabled.
 
-use std::io::{self, Read, Write};
+use std::io::{self, BufWriter, Read, Write};
 use std::path::Path;
 
+use super::atomic::{FileWriteOptions, OutputFile};
+use super::bincode_config::{
+    read_bincode_compressed_with_config, write_bincode_compressed_with_config, BincodeConfig,
+};
+use super::buffer::DEFAULT_BUFFER_SIZE;
+use super::envelope::CompressionCodec;
+use super::stream_compress::CompressionLevel;
+
 /// Serialize data to bincode format
 ///
 /// # Examples
@@ -46,6 +57,27 @@ pub fn from_bincode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> io::Result<
     bincode::deserialize(bytes).map_err(io::Error::other)
 }
 
+/// Deserialize bincode data, borrowing `&str` and `&[u8]` fields from `bytes`
+///
+/// Combined with [`unwrap_borrowed`](super::unwrap_borrowed) and a
+/// [`MappedFile`](super::MappedFile), fields can point straight into the mapping.
+///
+/// # Examples
+/// ```
+/// use embeddenator_io::{from_bincode_borrowed, to_bincode};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Entry<'a> { key: &'a str, value: &'a [u8] }
+///
+/// let bytes = to_bincode(&Entry { key: "id", value: b"payload" }).unwrap();
+/// let entry: Entry = from_bincode_borrowed(&bytes).unwrap();
+/// assert_eq!(entry.key, "id");
+/// ```
+pub fn from_bincode_borrowed<'a, T: serde::Deserialize<'a>>(bytes: &'a [u8]) -> io::Result<T> {
+    bincode::deserialize(bytes).map_err(io::Error::other)
+}
+
 /// Serialize data to JSON format (pretty-printed)
 ///
 /// # Examples
@@ -88,6 +120,14 @@ pub fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> io::Result<T> {
     serde_json::from_str(json).map_err(io::Error::other)
 }
 
+/// Deserialize JSON data, borrowing `&str` fields from `json`
+///
+/// Strings containing escape sequences cannot be borrowed; deserializing
+/// them into `&str` fails, so use `Cow<str>` fields where escapes may occur.
+pub fn from_json_borrowed<'a, T: serde::Deserialize<'a>>(json: &'a str) -> io::Result<T> {
+    serde_json::from_str(json).map_err(io::Error::other)
+}
+
 /// Write data to a file in bincode format
 ///
 /// # Examples
@@ -104,9 +144,20 @@ pub fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> io::Result<T> {
 pub fn write_bincode_file<P: AsRef<Path>, T: serde::Serialize>(
     path: P,
     value: &T,
+) -> io::Result<()> {
+    write_bincode_file_with_options(path, value, FileWriteOptions::default())
+}
+
+/// Write data to a file in bincode format with explicit file options
+pub fn write_bincode_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    opts: FileWriteOptions,
 ) -> io::Result<()> {
     let bytes = to_bincode(value)?;
-    std::fs::write(path, bytes)
+    let mut file = OutputFile::create(path, opts)?;
+    file.write_all(&bytes)?;
+    file.commit()
 }
 
 /// Read data from a file in bincode format
@@ -129,6 +180,53 @@ pub fn read_bincode_file<P: AsRef<Path>, T: serde::de::DeserializeOwned>(path: P
     from_bincode(&bytes)
 }
 
+/// Write data to a file as a bincode stream compressed with `codec`
+///
+/// Serialization streams straight into a
+/// [`StreamCompressor`](super::StreamCompressor), so neither the serialized
+/// nor the compressed form of `value` is ever held in memory. The file is a
+/// plain zstd or LZ4 frame (or raw bincode for `CompressionCodec::None`),
+/// readable with [`decompress_file`](super::decompress_file).
+///
+/// # Examples
+/// ```no_run
+/// use embeddenator_io::{read_bincode_compressed, write_bincode_compressed};
+/// use embeddenator_io::{CompressionCodec, CompressionLevel};
+///
+/// let values = vec![0u64; 1 << 20];
+/// let codec = CompressionCodec::Zstd;
+/// write_bincode_compressed("values.bin.zst", &values, codec, CompressionLevel::Default).unwrap();
+/// let loaded: Vec<u64> = read_bincode_compressed("values.bin.zst", codec).unwrap();
+/// assert_eq!(loaded.len(), values.len());
+/// ```
+pub fn write_bincode_compressed<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+) -> io::Result<()> {
+    write_bincode_compressed_with_options(path, value, codec, level, FileWriteOptions::default())
+}
+
+/// Write a compressed bincode stream to a file with explicit file options
+pub fn write_bincode_compressed_with_options<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    write_bincode_compressed_with_config(path, value, codec, level, BincodeConfig::default(), opts)
+}
+
+/// Read a file written by [`write_bincode_compressed`], decompressing while deserializing
+pub fn read_bincode_compressed<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+    path: P,
+    codec: CompressionCodec,
+) -> io::Result<T> {
+    read_bincode_compressed_with_config(path, codec, BincodeConfig::default())
+}
+
 /// Write data to a file in JSON format (pretty-printed)
 ///
 /// # Examples
@@ -143,8 +241,19 @@ pub fn read_bincode_file<P: AsRef<Path>, T: serde::de::DeserializeOwned>(path: P
 /// write_json_file("data.json", &data).unwrap();
 /// ```
 pub fn write_json_file<P: AsRef<Path>, T: serde::Serialize>(path: P, value: &T) -> io::Result<()> {
-    let file = std::fs::File::create(path)?;
-    serde_json::to_writer_pretty(file, value).map_err(io::Error::other)
+    write_json_file_with_options(path, value, FileWriteOptions::default())
+}
+
+/// Write data to a file in JSON format (pretty-printed) with explicit file options
+pub fn write_json_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let file = OutputFile::create(path, opts)?;
+    let mut writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, file);
+    serde_json::to_writer_pretty(&mut writer, value).map_err(io::Error::other)?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
 }
 
 /// Read data from a file in JSON format
@@ -201,21 +310,256 @@ pub fn read_json<R: Read, T: serde::de::DeserializeOwned>(reader: &mut R) -> io:
     serde_json::from_reader(reader).map_err(io::Error::other)
 }
 
+/// Serialize data to MessagePack format
+///
+/// Structs are encoded as maps keyed by field name, which is what most
+/// non-Rust MessagePack decoders expect.
+///
+/// # Examples
+/// ```
+/// use embeddenator_io::{from_msgpack, to_msgpack};
+///
+/// let bytes = to_msgpack(&vec![1u32, 2, 3]).unwrap();
+/// let decoded: Vec<u32> = from_msgpack(&bytes).unwrap();
+/// assert_eq!(decoded, vec![1, 2, 3]);
+/// ```
+#[cfg(feature = "format-msgpack")]
+pub fn to_msgpack<T: serde::Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    rmp_serde::to_vec_named(value).map_err(io::Error::other)
+}
+
+/// Deserialize data from MessagePack format
+#[cfg(feature = "format-msgpack")]
+pub fn from_msgpack<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    rmp_serde::from_slice(bytes).map_err(io::Error::other)
+}
+
+/// Write data to a file in MessagePack format
+#[cfg(feature = "format-msgpack")]
+pub fn write_msgpack_file<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+) -> io::Result<()> {
+    write_msgpack_file_with_options(path, value, FileWriteOptions::default())
+}
+
+/// Write data to a file in MessagePack format with explicit file options
+#[cfg(feature = "format-msgpack")]
+pub fn write_msgpack_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let bytes = to_msgpack(value)?;
+    let mut file = OutputFile::create(path, opts)?;
+    file.write_all(&bytes)?;
+    file.commit()
+}
+
+/// Read data from a file in MessagePack format
+#[cfg(feature = "format-msgpack")]
+pub fn read_msgpack_file<P: AsRef<Path>, T: serde::de::DeserializeOwned>(path: P) -> io::Result<T> {
+    let bytes = std::fs::read(path)?;
+    from_msgpack(&bytes)
+}
+
+/// Write data to a writer in MessagePack format
+#[cfg(feature = "format-msgpack")]
+pub fn write_msgpack<W: Write, T: serde::Serialize>(writer: &mut W, value: &T) -> io::Result<()> {
+    rmp_serde::encode::write_named(writer, value).map_err(io::Error::other)
+}
+
+/// Read one MessagePack value from a reader
+///
+/// Stops after the value, so several values can be read from one stream.
+#[cfg(feature = "format-msgpack")]
+pub fn read_msgpack<R: Read, T: serde::de::DeserializeOwned>(reader: &mut R) -> io::Result<T> {
+    rmp_serde::from_read(reader).map_err(io::Error::other)
+}
+
+/// Serialize data to CBOR format
+///
+/// # Examples
+/// ```
+/// use embeddenator_io::{from_cbor, to_cbor};
+///
+/// let bytes = to_cbor(&("key", 7u8)).unwrap();
+/// let decoded: (String, u8) = from_cbor(&bytes).unwrap();
+/// assert_eq!(decoded, ("key".to_string(), 7));
+/// ```
+#[cfg(feature = "format-cbor")]
+pub fn to_cbor<T: serde::Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(io::Error::other)?;
+    Ok(bytes)
+}
+
+/// Deserialize data from CBOR format
+#[cfg(feature = "format-cbor")]
+pub fn from_cbor<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    ciborium::from_reader(bytes).map_err(io::Error::other)
+}
+
+/// Write data to a file in CBOR format
+#[cfg(feature = "format-cbor")]
+pub fn write_cbor_file<P: AsRef<Path>, T: serde::Serialize>(path: P, value: &T) -> io::Result<()> {
+    write_cbor_file_with_options(path, value, FileWriteOptions::default())
+}
+
+/// Write data to a file in CBOR format with explicit file options
+#[cfg(feature = "format-cbor")]
+pub fn write_cbor_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let file = OutputFile::create(path, opts)?;
+    let mut writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, file);
+    ciborium::into_writer(value, &mut writer).map_err(io::Error::other)?;
+    writer.into_inner().map_err(|e| e.into_error())?.commit()
+}
+
+/// Read data from a file in CBOR format
+#[cfg(feature = "format-cbor")]
+pub fn read_cbor_file<P: AsRef<Path>, T: serde::de::DeserializeOwned>(path: P) -> io::Result<T> {
+    let file = std::fs::File::open(path)?;
+    ciborium::from_reader(std::io::BufReader::with_capacity(DEFAULT_BUFFER_SIZE, file))
+        .map_err(io::Error::other)
+}
+
+/// Write data to a writer in CBOR format
+#[cfg(feature = "format-cbor")]
+pub fn write_cbor<W: Write, T: serde::Serialize>(writer: &mut W, value: &T) -> io::Result<()> {
+    ciborium::into_writer(value, writer).map_err(io::Error::other)
+}
+
+/// Read one CBOR value from a reader
+///
+/// Stops after the value, so several values can be read from one stream.
+#[cfg(feature = "format-cbor")]
+pub fn read_cbor<R: Read, T: serde::de::DeserializeOwned>(reader: &mut R) -> io::Result<T> {
+    ciborium::from_reader(reader).map_err(io::Error::other)
+}
+
+/// Serialize data to postcard format
+///
+/// # Examples
+/// ```
+/// use embeddenator_io::{from_postcard, to_postcard};
+///
+/// let bytes = to_postcard(&300u32).unwrap();
+/// assert_eq!(bytes.len(), 2); // varint encoded
+/// assert_eq!(from_postcard::<u32>(&bytes).unwrap(), 300);
+/// ```
+#[cfg(feature = "format-postcard")]
+pub fn to_postcard<T: serde::Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    postcard::to_stdvec(value).map_err(io::Error::other)
+}
+
+/// Deserialize data from postcard format
+#[cfg(feature = "format-postcard")]
+pub fn from_postcard<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    postcard::from_bytes(bytes).map_err(io::Error::other)
+}
+
+/// Write data to a file in postcard format
+#[cfg(feature = "format-postcard")]
+pub fn write_postcard_file<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+) -> io::Result<()> {
+    write_postcard_file_with_options(path, value, FileWriteOptions::default())
+}
+
+/// Write data to a file in postcard format with explicit file options
+#[cfg(feature = "format-postcard")]
+pub fn write_postcard_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+    path: P,
+    value: &T,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let bytes = to_postcard(value)?;
+    let mut file = OutputFile::create(path, opts)?;
+    file.write_all(&bytes)?;
+    file.commit()
+}
+
+/// Read data from a file in postcard format
+#[cfg(feature = "format-postcard")]
+pub fn read_postcard_file<P: AsRef<Path>, T: serde::de::DeserializeOwned>(
+    path: P,
+) -> io::Result<T> {
+    let bytes = std::fs::read(path)?;
+    from_postcard(&bytes)
+}
+
+/// Write data to a writer in postcard format
+#[cfg(feature = "format-postcard")]
+pub fn write_postcard<W: Write, T: serde::Serialize>(writer: &mut W, value: &T) -> io::Result<()> {
+    let bytes = to_postcard(value)?;
+    writer.write_all(&bytes)
+}
+
+/// Read data from a reader in postcard format
+///
+/// postcard values are not self-delimiting, so this reads to the end of the stream.
+#[cfg(feature = "format-postcard")]
+pub fn read_postcard<R: Read, T: serde::de::DeserializeOwned>(reader: &mut R) -> io::Result<T> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    from_postcard(&bytes)
+}
+
 #[cfg(feature = "async")]
 pub mod async_serialize {
     //! Async variants of serialization functions
+    //!
+    //! The `_prefixed` helpers write each value after a little-endian `u32`
+    //! byte length, so one stream (e.g. a socket) can carry several values in
+    //! a row. The plain readers consume the stream to its end.
+    //!
+    //! # Examples
+    //! ```
+    //! use embeddenator_io::async_serialize::{read_bincode_prefixed, write_bincode_prefixed};
+    //!
+    //! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    //! let mut wire = Vec::new();
+    //! write_bincode_prefixed(&mut wire, &1u32).await.unwrap();
+    //! write_bincode_prefixed(&mut wire, &2u32).await.unwrap();
+    //!
+    //! let mut reader = &wire[..];
+    //! assert_eq!(read_bincode_prefixed::<_, u32>(&mut reader).await.unwrap(), Some(1));
+    //! assert_eq!(read_bincode_prefixed::<_, u32>(&mut reader).await.unwrap(), Some(2));
+    //! assert_eq!(read_bincode_prefixed::<_, u32>(&mut reader).await.unwrap(), None);
+    //! # });
+    //! ```
 
     use std::io;
     use std::path::Path;
-    use tokio::io::AsyncWriteExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::super::atomic::async_atomic::AsyncOutputFile;
+    use super::super::atomic::FileWriteOptions;
+    use super::super::format::SerializationFormat;
 
     /// Write data to a file in bincode format (async)
     pub async fn write_bincode_file<P: AsRef<Path>, T: serde::Serialize>(
         path: P,
         value: &T,
+    ) -> io::Result<()> {
+        write_bincode_file_with_options(path, value, FileWriteOptions::default()).await
+    }
+
+    /// Write data to a file in bincode format with explicit file options (async)
+    pub async fn write_bincode_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+        path: P,
+        value: &T,
+        opts: FileWriteOptions,
     ) -> io::Result<()> {
         let bytes = super::to_bincode(value)?;
-        tokio::fs::write(path, bytes).await
+        let mut file = AsyncOutputFile::create(path, opts).await?;
+        file.write_all(&bytes).await?;
+        file.commit().await
     }
 
     /// Read data from a file in bincode format (async)
@@ -230,9 +574,20 @@ pub mod async_serialize {
     pub async fn write_json_file<P: AsRef<Path>, T: serde::Serialize>(
         path: P,
         value: &T,
+    ) -> io::Result<()> {
+        write_json_file_with_options(path, value, FileWriteOptions::default()).await
+    }
+
+    /// Write data to a file in JSON format with explicit file options (async)
+    pub async fn write_json_file_with_options<P: AsRef<Path>, T: serde::Serialize>(
+        path: P,
+        value: &T,
+        opts: FileWriteOptions,
     ) -> io::Result<()> {
         let json = super::to_json_pretty(value)?;
-        tokio::fs::write(path, json.as_bytes()).await
+        let mut file = AsyncOutputFile::create(path, opts).await?;
+        file.write_all(json.as_bytes()).await?;
+        file.commit().await
     }
 
     /// Read data from a file in JSON format (async)
@@ -261,6 +616,221 @@ pub mod async_serialize {
         let json = super::to_json_pretty(value)?;
         writer.write_all(json.as_bytes()).await
     }
+
+    /// Read bincode data from an async reader, consuming it to the end
+    pub async fn read_bincode<R: AsyncReadExt + Unpin, T: serde::de::DeserializeOwned>(
+        reader: &mut R,
+    ) -> io::Result<T> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        super::from_bincode(&bytes)
+    }
+
+    /// Read JSON data from an async reader, consuming it to the end
+    pub async fn read_json<R: AsyncReadExt + Unpin, T: serde::de::DeserializeOwned>(
+        reader: &mut R,
+    ) -> io::Result<T> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        serde_json::from_slice(&bytes).map_err(io::Error::other)
+    }
+
+    /// Write `bytes` after a little-endian `u32` length prefix
+    pub async fn write_length_prefixed<W: AsyncWriteExt + Unpin>(
+        writer: &mut W,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "length-prefixed value exceeds u32::MAX bytes",
+            )
+        })?;
+        writer.write_all(&len.to_le_bytes()).await?;
+        writer.write_all(bytes).await
+    }
+
+    /// Read one length-prefixed value
+    ///
+    /// Returns `Ok(None)` on a clean end of stream before the prefix, and
+    /// `UnexpectedEof` if the stream ends inside a value. The buffer grows as
+    /// data arrives rather than trusting the prefix up front.
+    pub async fn read_length_prefixed<R: AsyncReadExt + Unpin>(
+        reader: &mut R,
+    ) -> io::Result<Option<Vec<u8>>> {
+        read_length_prefixed_limited(reader, None).await
+    }
+
+    /// Like [`read_length_prefixed`], rejecting lengths above `limit` before reading the value
+    pub(crate) async fn read_length_prefixed_limited<R: AsyncReadExt + Unpin>(
+        reader: &mut R,
+        limit: Option<u64>,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let mut prefix = [0u8; 4];
+        let mut filled = 0;
+        while filled < prefix.len() {
+            let n = reader.read(&mut prefix[filled..]).await?;
+            if n == 0 {
+                if filled == 0 {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated length prefix",
+                ));
+            }
+            filled += n;
+        }
+
+        let len = u32::from_le_bytes(prefix) as u64;
+        if limit.is_some_and(|limit| len > limit) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("length-prefixed value of {len} bytes exceeds limit"),
+            ));
+        }
+        let mut bytes = Vec::new();
+        (&mut *reader).take(len).read_to_end(&mut bytes).await?;
+        if bytes.len() as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated length-prefixed value",
+            ));
+        }
+        Ok(Some(bytes))
+    }
+
+    /// Write a length-prefixed bincode value
+    pub async fn write_bincode_prefixed<W: AsyncWriteExt + Unpin, T: serde::Serialize>(
+        writer: &mut W,
+        value: &T,
+    ) -> io::Result<()> {
+        write_length_prefixed(writer, &super::to_bincode(value)?).await
+    }
+
+    /// Read a length-prefixed bincode value; `Ok(None)` at end of stream
+    pub async fn read_bincode_prefixed<R: AsyncReadExt + Unpin, T: serde::de::DeserializeOwned>(
+        reader: &mut R,
+    ) -> io::Result<Option<T>> {
+        match read_length_prefixed(reader).await? {
+            Some(bytes) => super::from_bincode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a length-prefixed compact JSON value
+    pub async fn write_json_prefixed<W: AsyncWriteExt + Unpin, T: serde::Serialize>(
+        writer: &mut W,
+        value: &T,
+    ) -> io::Result<()> {
+        write_length_prefixed(writer, super::to_json(value)?.as_bytes()).await
+    }
+
+    /// Read a length-prefixed JSON value; `Ok(None)` at end of stream
+    pub async fn read_json_prefixed<R: AsyncReadExt + Unpin, T: serde::de::DeserializeOwned>(
+        reader: &mut R,
+    ) -> io::Result<Option<T>> {
+        match read_length_prefixed(reader).await? {
+            Some(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(io::Error::other),
+            None => Ok(None),
+        }
+    }
+
+    /// Write data to a file in the given format (async)
+    pub async fn write_format_file<F: SerializationFormat, P: AsRef<Path>, T: serde::Serialize>(
+        format: F,
+        path: P,
+        value: &T,
+    ) -> io::Result<()> {
+        write_format_file_with_options(format, path, value, FileWriteOptions::default()).await
+    }
+
+    /// Write data to a file in the given format with explicit file options (async)
+    pub async fn write_format_file_with_options<
+        F: SerializationFormat,
+        P: AsRef<Path>,
+        T: serde::Serialize,
+    >(
+        format: F,
+        path: P,
+        value: &T,
+        opts: FileWriteOptions,
+    ) -> io::Result<()> {
+        let bytes = format.encode(value)?;
+        let mut file = AsyncOutputFile::create(path, opts).await?;
+        file.write_all(&bytes).await?;
+        file.commit().await
+    }
+
+    /// Read data from a file in the given format (async)
+    pub async fn read_format_file<
+        F: SerializationFormat,
+        P: AsRef<Path>,
+        T: serde::de::DeserializeOwned,
+    >(
+        format: F,
+        path: P,
+    ) -> io::Result<T> {
+        let bytes = tokio::fs::read(path).await?;
+        format.decode(&bytes)
+    }
+
+    /// Write data to an async writer in the given format
+    pub async fn write_format<
+        F: SerializationFormat,
+        W: AsyncWriteExt + Unpin,
+        T: serde::Serialize,
+    >(
+        format: F,
+        writer: &mut W,
+        value: &T,
+    ) -> io::Result<()> {
+        writer.write_all(&format.encode(value)?).await
+    }
+
+    /// Read data from an async reader in the given format, reading it to the end
+    pub async fn read_format<
+        F: SerializationFormat,
+        R: AsyncReadExt + Unpin,
+        T: serde::de::DeserializeOwned,
+    >(
+        format: F,
+        reader: &mut R,
+    ) -> io::Result<T> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        format.decode(&bytes)
+    }
+
+    /// Write a length-prefixed value in the given format
+    pub async fn write_format_prefixed<
+        F: SerializationFormat,
+        W: AsyncWriteExt + Unpin,
+        T: serde::Serialize,
+    >(
+        format: F,
+        writer: &mut W,
+        value: &T,
+    ) -> io::Result<()> {
+        write_length_prefixed(writer, &format.encode(value)?).await
+    }
+
+    /// Read a length-prefixed value in the given format; `Ok(None)` at end of stream
+    pub async fn read_format_prefixed<
+        F: SerializationFormat,
+        R: AsyncReadExt + Unpin,
+        T: serde::de::DeserializeOwned,
+    >(
+        format: F,
+        reader: &mut R,
+    ) -> io::Result<Option<T>> {
+        match read_length_prefixed(reader).await? {
+            Some(bytes) => format.decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -268,7 +838,7 @@ mod tests {
     use super::*;
     use serde::{Deserialize, Serialize};
 
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
     struct TestData {
         id: u32,
         name: String,
@@ -308,4 +878,94 @@ mod tests {
         assert!(json.contains('\n')); // Pretty format has newlines
         assert!(json.contains("  ")); // Pretty format has indentation
     }
+
+    #[test]
+    fn test_bincode_compressed_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = vec![TestData::sample(); 1000];
+        let codecs = [
+            CompressionCodec::None,
+            #[cfg(feature = "compression-zstd")]
+            CompressionCodec::Zstd,
+            #[cfg(feature = "compression-lz4")]
+            CompressionCodec::Lz4,
+        ];
+        for codec in codecs {
+            let path = dir.path().join(codec.name());
+            write_bincode_compressed(&path, &data, codec, CompressionLevel::Default).unwrap();
+            let loaded: Vec<TestData> = read_bincode_compressed(&path, codec).unwrap();
+            assert_eq!(loaded, data);
+
+            // The file is a plain compressed stream of the bincode bytes
+            let raw = dir.path().join(format!("{}.raw", codec.name()));
+            crate::io::decompress_file(&path, &raw, codec).unwrap();
+            assert_eq!(std::fs::read(&raw).unwrap(), to_bincode(&data).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_borrowed_roundtrip() {
+        #[derive(Serialize, Deserialize)]
+        struct View<'a> {
+            id: u32,
+            name: &'a str,
+        }
+
+        let bytes = to_bincode(&TestData::sample()).unwrap();
+        let view: View = from_bincode_borrowed(&bytes).unwrap();
+        assert_eq!((view.id, view.name), (42, "test"));
+        assert!(bytes.as_ptr_range().contains(&view.name.as_ptr()));
+
+        let json = to_json(&TestData::sample()).unwrap();
+        let view: View = from_json_borrowed(&json).unwrap();
+        assert_eq!(view.name, "test");
+        assert!(json.as_bytes().as_ptr_range().contains(&view.name.as_ptr()));
+    }
+
+    #[test]
+    #[cfg(feature = "format-msgpack")]
+    fn test_msgpack_roundtrip() {
+        let data = TestData::sample();
+        let bytes = to_msgpack(&data).unwrap();
+        // Field names are kept for non-Rust readers
+        assert!(bytes.windows(4).any(|w| w == b"name"));
+        assert_eq!(from_msgpack::<TestData>(&bytes).unwrap(), data);
+
+        let mut stream = Vec::new();
+        write_msgpack(&mut stream, &1u8).unwrap();
+        write_msgpack(&mut stream, &data).unwrap();
+        let mut reader = &stream[..];
+        assert_eq!(read_msgpack::<_, u8>(&mut reader).unwrap(), 1);
+        assert_eq!(read_msgpack::<_, TestData>(&mut reader).unwrap(), data);
+    }
+
+    #[test]
+    #[cfg(feature = "format-cbor")]
+    fn test_cbor_roundtrip() {
+        let data = TestData::sample();
+        let bytes = to_cbor(&data).unwrap();
+        assert_eq!(from_cbor::<TestData>(&bytes).unwrap(), data);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.cbor");
+        write_cbor_file(&path, &data).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+        assert_eq!(read_cbor_file::<_, TestData>(&path).unwrap(), data);
+    }
+
+    #[test]
+    #[cfg(feature = "format-postcard")]
+    fn test_postcard_roundtrip() {
+        let data = TestData::sample();
+        let bytes = to_postcard(&data).unwrap();
+        assert!(bytes.len() < to_bincode(&data).unwrap().len());
+        assert_eq!(from_postcard::<TestData>(&bytes).unwrap(), data);
+
+        let mut stream = Vec::new();
+        write_postcard(&mut stream, &data).unwrap();
+        assert_eq!(
+            read_postcard::<_, TestData>(&mut &stream[..]).unwrap(),
+            data
+        );
+    }
 }
@@ -6,10 +6,15 @@
 use std::io::{self, Read, Write};
 use std::path::Path;
 
+use super::atomic::{FileWriteOptions, OutputFile};
+use super::durability::{Durability, Durable};
+use super::pool::{BufferPool, PooledBuffer};
+
 /// Stream reader for processing data in chunks
 pub struct StreamReader<R> {
     reader: R,
     buffer_size: usize,
+    pool: Option<BufferPool>,
 }
 
 impl<R: Read> StreamReader<R> {
@@ -23,16 +28,34 @@ impl<R: Read> StreamReader<R> {
         Self {
             reader,
             buffer_size,
+            pool: None,
         }
     }
 
+    /// Take read buffers from `pool`, shared with other readers
+    pub fn with_pool(mut self, pool: BufferPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Read buffer from the pool; without one, a single buffer is reused across calls
+    fn scratch(&mut self) -> PooledBuffer {
+        let size = self.buffer_size;
+        let mut buffer = self
+            .pool
+            .get_or_insert_with(|| BufferPool::with_capacity(size, 1))
+            .get();
+        buffer.resize(size, 0);
+        buffer
+    }
+
     /// Read all data and apply a transformation function
     pub fn read_all<F, T>(&mut self, mut transform: F) -> io::Result<Vec<T>>
     where
         F: FnMut(&[u8]) -> io::Result<T>,
     {
         let mut results = Vec::new();
-        let mut buffer = vec![0u8; self.buffer_size];
+        let mut buffer = self.scratch();
 
         loop {
             let n = self.reader.read(&mut buffer)?;
@@ -52,7 +75,7 @@ impl<R: Read> StreamReader<R> {
         F: FnMut(T, &[u8]) -> io::Result<T>,
     {
         let mut acc = init;
-        let mut buffer = vec![0u8; self.buffer_size];
+        let mut buffer = self.scratch();
 
         loop {
             let n = self.reader.read(&mut buffer)?;
@@ -127,12 +150,24 @@ impl<W: Write> StreamWriter<W> {
     }
 
     /// Finish writing and return the inner writer
+    ///
+    /// This only flushes to the OS; use [`finish_with`](Self::finish_with)
+    /// when the data must survive a power loss.
     pub fn finish(mut self) -> io::Result<W> {
         self.flush()?;
         Ok(self.writer)
     }
 }
 
+impl<W: Durable> StreamWriter<W> {
+    /// Finish writing, sync the inner writer according to `durability` and return it
+    pub fn finish_with(mut self, durability: Durability) -> io::Result<W> {
+        self.flush()?;
+        self.writer.sync(durability)?;
+        Ok(self.writer)
+    }
+}
+
 /// Write data to a file in streaming fashion
 ///
 /// # Examples
@@ -148,15 +183,28 @@ where
     I: Iterator<Item = D>,
     D: AsRef<[u8]>,
 {
-    let file = std::fs::File::create(path)?;
+    stream_write_file_with_options(path, chunks, FileWriteOptions::default())
+}
+
+/// Write data to a file in streaming fashion with explicit file options
+pub fn stream_write_file_with_options<P, I, D>(
+    path: P,
+    chunks: I,
+    opts: FileWriteOptions,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    I: Iterator<Item = D>,
+    D: AsRef<[u8]>,
+{
+    let file = OutputFile::create(path, opts)?;
     let mut writer = StreamWriter::new(file);
 
     for chunk in chunks {
         writer.write_chunk(chunk.as_ref())?;
     }
 
-    writer.flush()?;
-    Ok(())
+    writer.finish()?.commit()
 }
 
 /// Read a file in streaming fashion
@@ -198,14 +246,23 @@ pub mod async_stream {
 
     use std::io;
     use std::path::Path;
+
+    use futures::stream::{self, Stream};
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+    use super::super::atomic::async_atomic::AsyncOutputFile;
+    use super::super::atomic::FileWriteOptions;
+    use super::super::buffer::async_buffer::chunk_stream;
     use super::super::buffer::DEFAULT_BUFFER_SIZE;
+    use super::super::durability::async_durability::AsyncDurable;
+    use super::super::durability::Durability;
+    use super::super::pool::{BufferPool, PooledBuffer};
 
     /// Async stream reader
     pub struct AsyncStreamReader<R> {
         reader: R,
         buffer_size: usize,
+        pool: Option<BufferPool>,
     }
 
     impl<R: AsyncReadExt + Unpin> AsyncStreamReader<R> {
@@ -219,9 +276,16 @@ pub mod async_stream {
             Self {
                 reader,
                 buffer_size,
+                pool: None,
             }
         }
 
+        /// Take chunk buffers for [`read_all_pooled`](Self::read_all_pooled) from `pool`
+        pub fn with_pool(mut self, pool: BufferPool) -> Self {
+            self.pool = Some(pool);
+            self
+        }
+
         /// Read all data and apply async transformation
         pub async fn read_all<F, Fut, T>(&mut self, mut transform: F) -> io::Result<Vec<T>>
         where
@@ -243,6 +307,64 @@ pub mod async_stream {
             Ok(results)
         }
 
+        /// Read all data, handing each chunk to `transform` in a pooled buffer
+        ///
+        /// Unlike [`read_all`](Self::read_all), no chunk is copied: each one is
+        /// read straight into a buffer that returns to the pool once
+        /// `transform` drops it. Without [`with_pool`](Self::with_pool), a
+        /// pool sized to the buffer size is created on first use.
+        pub async fn read_all_pooled<F, Fut, T>(&mut self, mut transform: F) -> io::Result<Vec<T>>
+        where
+            F: FnMut(PooledBuffer) -> Fut,
+            Fut: std::future::Future<Output = io::Result<T>>,
+        {
+            let size = self.buffer_size;
+            let pool = self
+                .pool
+                .get_or_insert_with(|| BufferPool::new(size))
+                .clone();
+            let mut results = Vec::new();
+
+            loop {
+                let mut buffer = pool.get();
+                buffer.resize(size, 0);
+                let n = self.reader.read(&mut buffer).await?;
+                if n == 0 {
+                    break;
+                }
+                buffer.truncate(n);
+                results.push(transform(buffer).await?);
+            }
+
+            Ok(results)
+        }
+
+        /// Turn the reader into a stream of chunks of up to `buffer_size` bytes
+        pub fn into_stream(self) -> impl Stream<Item = io::Result<Vec<u8>>> {
+            chunk_stream(self.reader, self.buffer_size)
+        }
+
+        /// Turn the reader into a stream of chunks read into pooled buffers
+        ///
+        /// Without [`with_pool`](Self::with_pool), a pool sized to the buffer
+        /// size is created.
+        pub fn into_pooled_stream(self) -> impl Stream<Item = io::Result<PooledBuffer>> {
+            let size = self.buffer_size;
+            let pool = self.pool.unwrap_or_else(|| BufferPool::new(size));
+            stream::try_unfold(self.reader, move |mut reader| {
+                let mut buffer = pool.get();
+                async move {
+                    buffer.resize(size, 0);
+                    let n = reader.read(&mut buffer).await?;
+                    if n == 0 {
+                        return Ok(None);
+                    }
+                    buffer.truncate(n);
+                    Ok(Some((buffer, reader)))
+                }
+            })
+        }
+
         /// Count bytes asynchronously
         pub async fn count_bytes(&mut self) -> io::Result<u64> {
             let mut total = 0u64;
@@ -319,6 +441,15 @@ pub mod async_stream {
         }
     }
 
+    impl<W: AsyncDurable> AsyncStreamWriter<W> {
+        /// Finish writing, sync the inner writer according to `durability` and return it
+        pub async fn finish_with(mut self, durability: Durability) -> io::Result<W> {
+            self.flush().await?;
+            self.writer.sync(durability).await?;
+            Ok(self.writer)
+        }
+    }
+
     /// Stream write to file asynchronously
     pub async fn stream_write_file<P, I, D>(path: P, chunks: I) -> io::Result<()>
     where
@@ -326,15 +457,28 @@ pub mod async_stream {
         I: Iterator<Item = D>,
         D: AsRef<[u8]>,
     {
-        let file = tokio::fs::File::create(path).await?;
+        stream_write_file_with_options(path, chunks, FileWriteOptions::default()).await
+    }
+
+    /// Stream write to file asynchronously with explicit file options
+    pub async fn stream_write_file_with_options<P, I, D>(
+        path: P,
+        chunks: I,
+        opts: FileWriteOptions,
+    ) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        I: Iterator<Item = D>,
+        D: AsRef<[u8]>,
+    {
+        let file = AsyncOutputFile::create(path, opts).await?;
         let mut writer = AsyncStreamWriter::new(file);
 
         for chunk in chunks {
             writer.write_chunk(chunk.as_ref()).await?;
         }
 
-        writer.flush().await?;
-        Ok(())
+        writer.finish().await?.commit().await
     }
 }
 
@@ -366,6 +510,76 @@ mod tests {
         assert_eq!(buffer, b"Hello, world!");
     }
 
+    #[test]
+    fn test_stream_writer_finish_with_syncs_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("durable.bin");
+
+        let mut writer = StreamWriter::with_buffer_size(std::fs::File::create(&path).unwrap(), 64);
+        writer.write_chunk(b"durable").unwrap();
+        writer.finish_with(Durability::Data).unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"durable");
+    }
+
+    #[test]
+    fn test_stream_reader_with_pool_returns_buffer() {
+        let pool = BufferPool::new(4);
+        let mut reader =
+            StreamReader::with_buffer_size(Cursor::new(b"abcdefghij"), 4).with_pool(pool.clone());
+
+        let chunks = reader.read_all(|chunk| Ok(chunk.to_vec())).unwrap();
+        assert_eq!(
+            chunks,
+            vec![b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()]
+        );
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_read_all_pooled() {
+        use super::async_stream::AsyncStreamReader;
+
+        let pool = BufferPool::new(4);
+        let mut reader =
+            AsyncStreamReader::with_buffer_size(&b"abcdefghij"[..], 4).with_pool(pool.clone());
+
+        let lens = reader
+            .read_all_pooled(|chunk| async move { Ok(chunk.len()) })
+            .await
+            .unwrap();
+        assert_eq!(lens, vec![4, 4, 2]);
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_reader_streams() {
+        use super::async_stream::AsyncStreamReader;
+        use futures::{StreamExt, TryStreamExt};
+
+        let chunks: Vec<Vec<u8>> = AsyncStreamReader::with_buffer_size(&b"abcdefghij"[..], 4)
+            .into_stream()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            chunks,
+            vec![b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()]
+        );
+
+        let pool = BufferPool::new(4);
+        let total: usize = AsyncStreamReader::with_buffer_size(&b"abcdefghij"[..], 4)
+            .with_pool(pool.clone())
+            .into_pooled_stream()
+            .map(|chunk| chunk.unwrap().len())
+            .fold(0, |acc, n| async move { acc + n })
+            .await;
+        assert_eq!(total, 10);
+        assert_eq!(pool.available(), 1);
+    }
+
     #[test]
     fn test_stream_reader_fold() {
         let data = b"abcdefghij";
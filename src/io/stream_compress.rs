@@ -35,7 +35,9 @@
 
 use std::io::{self, Read, Write};
 
+use super::atomic::{FileWriteOptions, OutputFile};
 use super::envelope::CompressionCodec;
+use super::progress::{HookedReader, IoHooks};
 
 /// Compression level for streaming compression
 #[derive(Clone, Copy, Debug, Default)]
@@ -461,6 +463,35 @@ pub fn stream_decompress<R: Read, W: Write>(
     Ok(total_written)
 }
 
+/// [`stream_compress`] with progress reporting and cancellation
+///
+/// Progress is reported in uncompressed bytes read; the total is unknown.
+pub fn stream_compress_with_hooks<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    buffer_size: usize,
+    hooks: &IoHooks,
+) -> io::Result<u64> {
+    let reader = HookedReader::new(reader, hooks, None);
+    stream_compress(reader, writer, codec, level, buffer_size)
+}
+
+/// [`stream_decompress`] with progress reporting and cancellation
+///
+/// Progress is reported in compressed bytes read; the total is unknown.
+pub fn stream_decompress_with_hooks<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    codec: CompressionCodec,
+    buffer_size: usize,
+    hooks: &IoHooks,
+) -> io::Result<u64> {
+    let reader = HookedReader::new(reader, hooks, None);
+    stream_decompress(reader, writer, codec, buffer_size)
+}
+
 /// Stream-compress a file to another file
 ///
 /// Convenience function for file-to-file streaming compression.
@@ -478,12 +509,63 @@ pub fn compress_file<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
     output_path: Q,
     codec: CompressionCodec,
     level: CompressionLevel,
+) -> io::Result<(u64, u64)> {
+    compress_file_with_hooks(input_path, output_path, codec, level, &IoHooks::default())
+}
+
+/// Stream-compress a file with explicit file options
+pub fn compress_file_with_options<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    input_path: P,
+    output_path: Q,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    opts: FileWriteOptions,
+) -> io::Result<(u64, u64)> {
+    compress_file_inner(
+        input_path.as_ref(),
+        output_path.as_ref(),
+        codec,
+        level,
+        &IoHooks::default(),
+        opts,
+    )
+}
+
+/// Stream-compress a file, reporting progress and honouring cancellation
+///
+/// Progress is reported in uncompressed bytes read, against the input file size.
+pub fn compress_file_with_hooks<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    input_path: P,
+    output_path: Q,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    hooks: &IoHooks,
+) -> io::Result<(u64, u64)> {
+    compress_file_inner(
+        input_path.as_ref(),
+        output_path.as_ref(),
+        codec,
+        level,
+        hooks,
+        FileWriteOptions::default(),
+    )
+}
+
+fn compress_file_inner(
+    input_path: &std::path::Path,
+    output_path: &std::path::Path,
+    codec: CompressionCodec,
+    level: CompressionLevel,
+    hooks: &IoHooks,
+    opts: FileWriteOptions,
 ) -> io::Result<(u64, u64)> {
     let input = std::fs::File::open(input_path)?;
     let input_size = input.metadata()?.len();
-    let output = std::fs::File::create(output_path.as_ref())?;
+    let mut output = OutputFile::create(output_path, opts)?;
 
-    stream_compress(input, &output, codec, level, 64 * 1024)?;
+    let input = HookedReader::new(input, hooks, Some(input_size));
+    stream_compress(input, &mut output, codec, level, 64 * 1024)?;
+    output.commit()?;
 
     let output_size = std::fs::metadata(output_path)?.len();
     Ok((input_size, output_size))
@@ -505,16 +587,500 @@ pub fn decompress_file<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
     output_path: Q,
     codec: CompressionCodec,
 ) -> io::Result<(u64, u64)> {
-    let input = std::fs::File::open(input_path.as_ref())?;
+    decompress_file_with_hooks(input_path, output_path, codec, &IoHooks::default())
+}
+
+/// Stream-decompress a file with explicit file options
+pub fn decompress_file_with_options<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    input_path: P,
+    output_path: Q,
+    codec: CompressionCodec,
+    opts: FileWriteOptions,
+) -> io::Result<(u64, u64)> {
+    decompress_file_inner(
+        input_path.as_ref(),
+        output_path.as_ref(),
+        codec,
+        &IoHooks::default(),
+        opts,
+    )
+}
+
+/// Stream-decompress a file, reporting progress and honouring cancellation
+///
+/// Progress is reported in compressed bytes read, against the input file size.
+pub fn decompress_file_with_hooks<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    input_path: P,
+    output_path: Q,
+    codec: CompressionCodec,
+    hooks: &IoHooks,
+) -> io::Result<(u64, u64)> {
+    decompress_file_inner(
+        input_path.as_ref(),
+        output_path.as_ref(),
+        codec,
+        hooks,
+        FileWriteOptions::default(),
+    )
+}
+
+fn decompress_file_inner(
+    input_path: &std::path::Path,
+    output_path: &std::path::Path,
+    codec: CompressionCodec,
+    hooks: &IoHooks,
+    opts: FileWriteOptions,
+) -> io::Result<(u64, u64)> {
+    let input = std::fs::File::open(input_path)?;
     let input_size = input.metadata()?.len();
-    let output = std::fs::File::create(output_path.as_ref())?;
+    let mut output = OutputFile::create(output_path, opts)?;
 
-    stream_decompress(input, &output, codec, 64 * 1024)?;
+    let input = HookedReader::new(input, hooks, Some(input_size));
+    stream_decompress(input, &mut output, codec, 64 * 1024)?;
+    output.commit()?;
 
     let output_size = std::fs::metadata(output_path)?.len();
     Ok((input_size, output_size))
 }
 
+#[cfg(feature = "async")]
+pub mod async_stream_compress {
+    //! Async streaming compression and decompression
+    //!
+    //! Produces and reads the same zstd and LZ4 frame streams as
+    //! [`StreamCompressor`](super::StreamCompressor) and
+    //! [`StreamDecompressor`](super::StreamDecompressor), without blocking the
+    //! runtime.
+    //!
+    //! # Examples
+    //! ```no_run
+    //! use embeddenator_io::async_stream_compress::compress_file;
+    //! use embeddenator_io::{CompressionCodec, CompressionLevel};
+    //!
+    //! # async fn run() -> std::io::Result<()> {
+    //! let (raw, packed) =
+    //!     compress_file("engram.bin", "engram.bin.zst", CompressionCodec::Zstd, CompressionLevel::Default)
+    //!         .await?;
+    //! # Ok(())
+    //! # }
+    //! ```
+
+    use std::io;
+    use std::path::Path;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    #[cfg(any(feature = "compression-zstd", feature = "compression-lz4"))]
+    use tokio::io::BufReader;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+    use super::super::atomic::async_atomic::AsyncOutputFile;
+    use super::super::atomic::FileWriteOptions;
+    use super::super::envelope::CompressionCodec;
+    use super::CompressionLevel;
+
+    /// Async streaming compressor that wraps an [`AsyncWrite`]
+    pub struct AsyncStreamCompressor<W: AsyncWrite + Unpin> {
+        inner: AsyncCompressorInner<W>,
+        codec: CompressionCodec,
+    }
+
+    enum AsyncCompressorInner<W: AsyncWrite + Unpin> {
+        #[cfg(feature = "compression-zstd")]
+        Zstd(async_compression::tokio::write::ZstdEncoder<KeepOpen<W>>),
+        #[cfg(feature = "compression-lz4")]
+        Lz4(async_compression::tokio::write::Lz4Encoder<KeepOpen<W>>),
+        /// Passthrough when no compression is used
+        None(KeepOpen<W>),
+    }
+
+    impl<W: AsyncWrite + Unpin> AsyncStreamCompressor<W> {
+        /// Create an async streaming zstd compressor
+        #[cfg(feature = "compression-zstd")]
+        pub fn zstd(writer: W, level: CompressionLevel) -> io::Result<Self> {
+            let level = async_compression::Level::Precise(level.to_zstd_level());
+            Ok(Self {
+                inner: AsyncCompressorInner::Zstd(
+                    async_compression::tokio::write::ZstdEncoder::with_quality(
+                        KeepOpen(writer),
+                        level,
+                    ),
+                ),
+                codec: CompressionCodec::Zstd,
+            })
+        }
+
+        /// Create an async streaming zstd compressor (stub when feature disabled)
+        #[cfg(not(feature = "compression-zstd"))]
+        pub fn zstd(_writer: W, _level: CompressionLevel) -> io::Result<Self> {
+            Err(io::Error::other(
+                "zstd streaming compression requires feature `compression-zstd`",
+            ))
+        }
+
+        /// Create an async streaming LZ4 frame compressor
+        #[cfg(feature = "compression-lz4")]
+        pub fn lz4(writer: W, _level: CompressionLevel) -> io::Result<Self> {
+            Ok(Self {
+                inner: AsyncCompressorInner::Lz4(async_compression::tokio::write::Lz4Encoder::new(
+                    KeepOpen(writer),
+                )),
+                codec: CompressionCodec::Lz4,
+            })
+        }
+
+        /// Create an async streaming LZ4 compressor (stub when feature disabled)
+        #[cfg(not(feature = "compression-lz4"))]
+        pub fn lz4(_writer: W, _level: CompressionLevel) -> io::Result<Self> {
+            Err(io::Error::other(
+                "lz4 streaming compression requires feature `compression-lz4`",
+            ))
+        }
+
+        /// Create a passthrough compressor (no compression)
+        pub fn none(writer: W) -> Self {
+            Self {
+                inner: AsyncCompressorInner::None(KeepOpen(writer)),
+                codec: CompressionCodec::None,
+            }
+        }
+
+        /// Create an async streaming compressor with the specified codec
+        pub fn with_codec(
+            writer: W,
+            codec: CompressionCodec,
+            level: CompressionLevel,
+        ) -> io::Result<Self> {
+            match codec {
+                CompressionCodec::None => Ok(Self::none(writer)),
+                CompressionCodec::Zstd => Self::zstd(writer, level),
+                CompressionCodec::Lz4 => Self::lz4(writer, level),
+            }
+        }
+
+        /// Get the compression codec being used
+        pub fn codec(&self) -> CompressionCodec {
+            self.codec
+        }
+
+        /// Finish compression and return the underlying writer
+        ///
+        /// This writes the end of the compressed stream and flushes the
+        /// writer, but does not shut it down.
+        pub async fn finish(mut self) -> io::Result<W> {
+            self.shutdown().await?;
+            Ok(match self.inner {
+                #[cfg(feature = "compression-zstd")]
+                AsyncCompressorInner::Zstd(encoder) => encoder.into_inner().0,
+                #[cfg(feature = "compression-lz4")]
+                AsyncCompressorInner::Lz4(encoder) => encoder.into_inner().0,
+                AsyncCompressorInner::None(writer) => writer.0,
+            })
+        }
+    }
+
+    impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncStreamCompressor<W> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            match &mut self.get_mut().inner {
+                #[cfg(feature = "compression-zstd")]
+                AsyncCompressorInner::Zstd(encoder) => Pin::new(encoder).poll_write(cx, buf),
+                #[cfg(feature = "compression-lz4")]
+                AsyncCompressorInner::Lz4(encoder) => Pin::new(encoder).poll_write(cx, buf),
+                AsyncCompressorInner::None(writer) => Pin::new(writer).poll_write(cx, buf),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().inner {
+                #[cfg(feature = "compression-zstd")]
+                AsyncCompressorInner::Zstd(encoder) => Pin::new(encoder).poll_flush(cx),
+                #[cfg(feature = "compression-lz4")]
+                AsyncCompressorInner::Lz4(encoder) => Pin::new(encoder).poll_flush(cx),
+                AsyncCompressorInner::None(writer) => Pin::new(writer).poll_flush(cx),
+            }
+        }
+
+        /// Ends the compressed stream; the underlying writer is flushed, not shut down
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().inner {
+                #[cfg(feature = "compression-zstd")]
+                AsyncCompressorInner::Zstd(encoder) => Pin::new(encoder).poll_shutdown(cx),
+                #[cfg(feature = "compression-lz4")]
+                AsyncCompressorInner::Lz4(encoder) => Pin::new(encoder).poll_shutdown(cx),
+                AsyncCompressorInner::None(writer) => Pin::new(writer).poll_shutdown(cx),
+            }
+        }
+    }
+
+    /// Writer adapter whose shutdown only flushes
+    ///
+    /// The encoders shut down their writer once the stream is finished; this
+    /// keeps the caller's writer usable after [`AsyncStreamCompressor::finish`].
+    struct KeepOpen<W>(W);
+
+    impl<W: AsyncWrite + Unpin> AsyncWrite for KeepOpen<W> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().0).poll_flush(cx)
+        }
+    }
+
+    /// Async streaming decompressor that wraps an [`AsyncRead`]
+    ///
+    /// Concatenated frames are decoded as one stream, matching
+    /// [`StreamDecompressor`](super::StreamDecompressor).
+    pub struct AsyncStreamDecompressor<R: AsyncRead + Unpin> {
+        inner: AsyncDecompressorInner<R>,
+        codec: CompressionCodec,
+    }
+
+    enum AsyncDecompressorInner<R: AsyncRead + Unpin> {
+        #[cfg(feature = "compression-zstd")]
+        Zstd(async_compression::tokio::bufread::ZstdDecoder<BufReader<R>>),
+        #[cfg(feature = "compression-lz4")]
+        Lz4(async_compression::tokio::bufread::Lz4Decoder<BufReader<R>>),
+        /// Passthrough when no decompression is used
+        None(R),
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncStreamDecompressor<R> {
+        /// Create an async streaming zstd decompressor
+        #[cfg(feature = "compression-zstd")]
+        pub fn zstd(reader: R) -> io::Result<Self> {
+            let mut decoder =
+                async_compression::tokio::bufread::ZstdDecoder::new(BufReader::new(reader));
+            decoder.multiple_members(true);
+            Ok(Self {
+                inner: AsyncDecompressorInner::Zstd(decoder),
+                codec: CompressionCodec::Zstd,
+            })
+        }
+
+        /// Create an async streaming zstd decompressor (stub when feature disabled)
+        #[cfg(not(feature = "compression-zstd"))]
+        pub fn zstd(_reader: R) -> io::Result<Self> {
+            Err(io::Error::other(
+                "zstd streaming decompression requires feature `compression-zstd`",
+            ))
+        }
+
+        /// Create an async streaming LZ4 frame decompressor
+        #[cfg(feature = "compression-lz4")]
+        pub fn lz4(reader: R) -> io::Result<Self> {
+            let mut decoder =
+                async_compression::tokio::bufread::Lz4Decoder::new(BufReader::new(reader));
+            decoder.multiple_members(true);
+            Ok(Self {
+                inner: AsyncDecompressorInner::Lz4(decoder),
+                codec: CompressionCodec::Lz4,
+            })
+        }
+
+        /// Create an async streaming LZ4 decompressor (stub when feature disabled)
+        #[cfg(not(feature = "compression-lz4"))]
+        pub fn lz4(_reader: R) -> io::Result<Self> {
+            Err(io::Error::other(
+                "lz4 streaming decompression requires feature `compression-lz4`",
+            ))
+        }
+
+        /// Create a passthrough decompressor (no decompression)
+        pub fn none(reader: R) -> Self {
+            Self {
+                inner: AsyncDecompressorInner::None(reader),
+                codec: CompressionCodec::None,
+            }
+        }
+
+        /// Create an async streaming decompressor with the specified codec
+        pub fn with_codec(reader: R, codec: CompressionCodec) -> io::Result<Self> {
+            match codec {
+                CompressionCodec::None => Ok(Self::none(reader)),
+                CompressionCodec::Zstd => Self::zstd(reader),
+                CompressionCodec::Lz4 => Self::lz4(reader),
+            }
+        }
+
+        /// Get the compression codec being used
+        pub fn codec(&self) -> CompressionCodec {
+            self.codec
+        }
+
+        /// Get the inner reader (consumes the decompressor)
+        ///
+        /// Compressed input already buffered by the decoder is discarded.
+        pub fn into_inner(self) -> R {
+            match self.inner {
+                #[cfg(feature = "compression-zstd")]
+                AsyncDecompressorInner::Zstd(decoder) => decoder.into_inner().into_inner(),
+                #[cfg(feature = "compression-lz4")]
+                AsyncDecompressorInner::Lz4(decoder) => decoder.into_inner().into_inner(),
+                AsyncDecompressorInner::None(reader) => reader,
+            }
+        }
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for AsyncStreamDecompressor<R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            match &mut self.get_mut().inner {
+                #[cfg(feature = "compression-zstd")]
+                AsyncDecompressorInner::Zstd(decoder) => Pin::new(decoder).poll_read(cx, buf),
+                #[cfg(feature = "compression-lz4")]
+                AsyncDecompressorInner::Lz4(decoder) => Pin::new(decoder).poll_read(cx, buf),
+                AsyncDecompressorInner::None(reader) => Pin::new(reader).poll_read(cx, buf),
+            }
+        }
+    }
+
+    /// Stream-compress data from an async reader to an async writer
+    ///
+    /// Returns the number of uncompressed bytes read.
+    pub async fn stream_compress<R, W>(
+        mut reader: R,
+        writer: W,
+        codec: CompressionCodec,
+        level: CompressionLevel,
+        buffer_size: usize,
+    ) -> io::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut compressor = AsyncStreamCompressor::with_codec(writer, codec, level)?;
+        let mut buffer = vec![0u8; buffer_size];
+        let mut total_written = 0u64;
+
+        loop {
+            let bytes_read = reader.read(&mut buffer).await?;
+            if bytes_read == 0 {
+                break;
+            }
+            compressor.write_all(&buffer[..bytes_read]).await?;
+            total_written += bytes_read as u64;
+        }
+
+        compressor.finish().await?;
+        Ok(total_written)
+    }
+
+    /// Stream-decompress data from an async reader to an async writer
+    ///
+    /// Returns the number of uncompressed bytes written.
+    pub async fn stream_decompress<R, W>(
+        reader: R,
+        mut writer: W,
+        codec: CompressionCodec,
+        buffer_size: usize,
+    ) -> io::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut decompressor = AsyncStreamDecompressor::with_codec(reader, codec)?;
+        let mut buffer = vec![0u8; buffer_size];
+        let mut total_written = 0u64;
+
+        loop {
+            let bytes_read = decompressor.read(&mut buffer).await?;
+            if bytes_read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..bytes_read]).await?;
+            total_written += bytes_read as u64;
+        }
+
+        writer.flush().await?;
+        Ok(total_written)
+    }
+
+    /// Stream-compress a file to another file (async)
+    ///
+    /// Returns (uncompressed_size, compressed_size).
+    pub async fn compress_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        input_path: P,
+        output_path: Q,
+        codec: CompressionCodec,
+        level: CompressionLevel,
+    ) -> io::Result<(u64, u64)> {
+        compress_file_with_options(
+            input_path,
+            output_path,
+            codec,
+            level,
+            FileWriteOptions::default(),
+        )
+        .await
+    }
+
+    /// Stream-compress a file with explicit file options (async)
+    pub async fn compress_file_with_options<P: AsRef<Path>, Q: AsRef<Path>>(
+        input_path: P,
+        output_path: Q,
+        codec: CompressionCodec,
+        level: CompressionLevel,
+        opts: FileWriteOptions,
+    ) -> io::Result<(u64, u64)> {
+        let input = tokio::fs::File::open(input_path).await?;
+        let input_size = input.metadata().await?.len();
+        let mut output = AsyncOutputFile::create(&output_path, opts).await?;
+
+        stream_compress(input, &mut output, codec, level, 64 * 1024).await?;
+        output.commit().await?;
+
+        let output_size = tokio::fs::metadata(output_path).await?.len();
+        Ok((input_size, output_size))
+    }
+
+    /// Stream-decompress a file to another file (async)
+    ///
+    /// Returns (compressed_size, uncompressed_size).
+    pub async fn decompress_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        input_path: P,
+        output_path: Q,
+        codec: CompressionCodec,
+    ) -> io::Result<(u64, u64)> {
+        decompress_file_with_options(input_path, output_path, codec, FileWriteOptions::default())
+            .await
+    }
+
+    /// Stream-decompress a file with explicit file options (async)
+    pub async fn decompress_file_with_options<P: AsRef<Path>, Q: AsRef<Path>>(
+        input_path: P,
+        output_path: Q,
+        codec: CompressionCodec,
+        opts: FileWriteOptions,
+    ) -> io::Result<(u64, u64)> {
+        let input = tokio::fs::File::open(input_path).await?;
+        let input_size = input.metadata().await?.len();
+        let mut output = AsyncOutputFile::create(&output_path, opts).await?;
+
+        stream_decompress(input, &mut output, codec, 64 * 1024).await?;
+        output.commit().await?;
+
+        let output_size = tokio::fs::metadata(output_path).await?.len();
+        Ok((input_size, output_size))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -573,6 +1139,45 @@ mod tests {
         assert_eq!(decompressed, data);
     }
 
+    #[test]
+    fn test_stream_compress_with_hooks_reports_and_cancels() {
+        use crate::{is_cancelled, CancellationToken};
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let data = vec![5u8; 10_000];
+        let last = Arc::new(AtomicU64::new(0));
+        let seen = last.clone();
+        let hooks = IoHooks::new().with_progress(move |done: u64, _total: Option<u64>| {
+            seen.store(done, Ordering::Relaxed);
+        });
+
+        let mut compressed = Vec::new();
+        stream_compress_with_hooks(
+            Cursor::new(&data),
+            &mut compressed,
+            CompressionCodec::None,
+            CompressionLevel::Default,
+            1024,
+            &hooks,
+        )
+        .unwrap();
+        assert_eq!(last.load(Ordering::Relaxed), 10_000);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let hooks = IoHooks::new().with_cancellation(token);
+        let err = stream_decompress_with_hooks(
+            Cursor::new(&compressed),
+            Vec::new(),
+            CompressionCodec::None,
+            1024,
+            &hooks,
+        )
+        .unwrap_err();
+        assert!(is_cancelled(&err));
+    }
+
     #[cfg(feature = "compression-zstd")]
     #[test]
     fn test_zstd_streaming_roundtrip() {
@@ -692,4 +1297,102 @@ mod tests {
         assert_eq!(CompressionLevel::Default.to_lz4_level(), 4);
         assert_eq!(CompressionLevel::Best.to_lz4_level(), 9);
     }
+
+    /// Async output must decode with the sync decompressor and vice versa
+    #[cfg(feature = "async")]
+    async fn check_async_interop(codec: CompressionCodec) {
+        use super::async_stream_compress::{
+            stream_compress as compress_async, AsyncStreamDecompressor,
+        };
+        use tokio::io::AsyncReadExt;
+
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut compressed = Vec::new();
+        let read = compress_async(
+            &data[..],
+            &mut compressed,
+            codec,
+            CompressionLevel::Fast,
+            4096,
+        )
+        .await
+        .unwrap();
+        assert_eq!(read, data.len() as u64);
+        let mut decoded = Vec::new();
+        StreamDecompressor::with_codec(Cursor::new(&compressed), codec)
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+
+        let mut compressed = Vec::new();
+        stream_compress(
+            Cursor::new(&data),
+            &mut compressed,
+            codec,
+            CompressionLevel::Fast,
+            4096,
+        )
+        .unwrap();
+        let mut decoded = Vec::new();
+        AsyncStreamDecompressor::with_codec(&compressed[..], codec)
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .await
+            .unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_passthrough_interop() {
+        check_async_interop(CompressionCodec::None).await;
+    }
+
+    #[cfg(all(feature = "async", feature = "compression-zstd"))]
+    #[tokio::test]
+    async fn test_async_zstd_interop() {
+        check_async_interop(CompressionCodec::Zstd).await;
+    }
+
+    #[cfg(all(feature = "async", feature = "compression-lz4"))]
+    #[tokio::test]
+    async fn test_async_lz4_interop() {
+        check_async_interop(CompressionCodec::Lz4).await;
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_compress_file_roundtrip() {
+        use super::async_stream_compress::{compress_file, decompress_file, AsyncStreamCompressor};
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.bin");
+        let packed = dir.path().join("input.bin.pack");
+        let output = dir.path().join("output.bin");
+        std::fs::write(&input, vec![9u8; 50_000]).unwrap();
+
+        let codec = if cfg!(feature = "compression-zstd") {
+            CompressionCodec::Zstd
+        } else {
+            CompressionCodec::None
+        };
+        let (raw, _) = compress_file(&input, &packed, codec, CompressionLevel::Default)
+            .await
+            .unwrap();
+        assert_eq!(raw, 50_000);
+        let (_, restored) = decompress_file(&packed, &output, codec).await.unwrap();
+        assert_eq!(restored, 50_000);
+        assert_eq!(std::fs::read(&output).unwrap(), vec![9u8; 50_000]);
+
+        if !CompressionCodec::Lz4.is_available() {
+            let err = AsyncStreamCompressor::with_codec(
+                Vec::new(),
+                CompressionCodec::Lz4,
+                CompressionLevel::Default,
+            );
+            assert!(err.is_err());
+        }
+    }
 }
@@ -0,0 +1,391 @@
+//! Schema versioning and migration for persisted types
+//!
+//! Decoding bincode written for an older struct layout fails with an opaque
+//! error, or worse, succeeds with garbage. [`to_versioned`] stores the schema
+//! version in front of the payload, and a [`MigrationRegistry`] reads any
+//! registered older version and upgrades it one step at a time into the
+//! current type.
+//!
+//! Layout:
+//! ```text
+//! "EDV1" | version u32 LE | payload (encoded with the chosen format)
+//! ```
+//! Data without the `EDV1` prefix was written before versioning and is read
+//! as the registry's legacy version (0 unless changed). The prefix goes
+//! inside the payload, so versioned data can itself be wrapped in an
+//! envelope or stored through any [`SerializationFormat`].
+//!
+//! # Examples
+//! ```
+//! use embeddenator_io::{to_versioned, Bincode, MigrationRegistry, Versioned};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct EngramV1 { dim: u32 }
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Engram { dim: u32, sparsity: f32 }
+//!
+//! impl Versioned for EngramV1 { const VERSION: u32 = 1; }
+//! impl Versioned for Engram { const VERSION: u32 = 2; }
+//!
+//! let old = to_versioned(Bincode, &EngramV1 { dim: 64 }).unwrap();
+//!
+//! let registry = MigrationRegistry::<Engram>::new(Bincode)
+//!     .migrate(1, |v1: EngramV1| Engram { dim: v1.dim, sparsity: 0.0 });
+//! let engram = registry.decode(&old).unwrap();
+//! assert_eq!(engram, Engram { dim: 64, sparsity: 0.0 });
+//! ```
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use super::atomic::{FileWriteOptions, OutputFile};
+use super::envelope::{unwrap_auto, PayloadKind};
+use super::format::{Bincode, SerializationFormat};
+
+const MAGIC: [u8; 4] = *b"EDV1";
+const HEADER_LEN: usize = 8;
+
+/// Type persisted with a schema version
+///
+/// Bump `VERSION` whenever the serialized layout changes, and register a
+/// migration from the previous version.
+pub trait Versioned: serde::Serialize + serde::de::DeserializeOwned {
+    /// Current schema version of this type
+    const VERSION: u32;
+}
+
+/// Serialize `value` with `format`, prefixed with its schema version
+pub fn to_versioned<F: SerializationFormat, T: Versioned>(
+    format: F,
+    value: &T,
+) -> io::Result<Vec<u8>> {
+    let payload = format.encode(value)?;
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&T::VERSION.to_le_bytes());
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
+
+/// Split versioned data into (schema version, payload)
+///
+/// Returns `None` for data written without a version prefix.
+pub fn split_versioned(data: &[u8]) -> Option<(u32, &[u8])> {
+    if data.len() < HEADER_LEN || data[..4] != MAGIC {
+        return None;
+    }
+    let version = u32::from_le_bytes(data[4..8].try_into().expect("4-byte slice"));
+    Some((version, &data[HEADER_LEN..]))
+}
+
+/// Deserialize versioned data, requiring exactly `T::VERSION`
+///
+/// Use a [`MigrationRegistry`] to accept older versions.
+pub fn from_versioned<F: SerializationFormat, T: Versioned>(
+    format: F,
+    data: &[u8],
+) -> io::Result<T> {
+    match split_versioned(data) {
+        Some((version, payload)) if version == T::VERSION => format.decode(payload),
+        Some((version, _)) => Err(version_mismatch(version, T::VERSION)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "data has no schema version",
+        )),
+    }
+}
+
+/// Write `value` to a file as versioned data
+pub fn write_versioned_file<F: SerializationFormat, P: AsRef<Path>, T: Versioned>(
+    format: F,
+    path: P,
+    value: &T,
+) -> io::Result<()> {
+    write_versioned_file_with_options(format, path, value, FileWriteOptions::default())
+}
+
+/// Write `value` to a file as versioned data with explicit file options
+pub fn write_versioned_file_with_options<F: SerializationFormat, P: AsRef<Path>, T: Versioned>(
+    format: F,
+    path: P,
+    value: &T,
+    opts: FileWriteOptions,
+) -> io::Result<()> {
+    let bytes = to_versioned(format, value)?;
+    let mut file = OutputFile::create(path, opts)?;
+    file.write_all(&bytes)?;
+    file.commit()
+}
+
+fn version_mismatch(found: u32, expected: u32) -> io::Error {
+    let detail = if found > expected {
+        "newer than this build supports"
+    } else {
+        "no migration registered"
+    };
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("schema version {found}, expected {expected} ({detail})"),
+    )
+}
+
+type AnyValue = Box<dyn Any>;
+
+/// One upgrade step from version `from` to `from + 1`
+struct Migration<F> {
+    decode: fn(&F, &[u8]) -> io::Result<AnyValue>,
+    upgrade: Box<dyn Fn(AnyValue) -> io::Result<AnyValue> + Send + Sync>,
+}
+
+fn decode_any<F: SerializationFormat, T: serde::de::DeserializeOwned + 'static>(
+    format: &F,
+    payload: &[u8],
+) -> io::Result<AnyValue> {
+    Ok(Box::new(format.decode::<T>(payload)?))
+}
+
+/// Reads versioned data and upgrades older versions into `T`
+///
+/// Each migration upgrades one version to the next, so data written at
+/// version 1 of a type now at version 4 runs the 1→2, 2→3 and 3→4 steps in
+/// order. A step's input type must be the previous step's output type.
+pub struct MigrationRegistry<T: Versioned, F: SerializationFormat = Bincode> {
+    format: F,
+    legacy_version: u32,
+    migrations: BTreeMap<u32, Migration<F>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Versioned + 'static, F: SerializationFormat> MigrationRegistry<T, F> {
+    /// Create a registry reading payloads encoded with `format`
+    pub fn new(format: F) -> Self {
+        Self {
+            format,
+            legacy_version: 0,
+            migrations: BTreeMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Schema version assumed for data without a version prefix (default: 0)
+    pub fn legacy_version(mut self, version: u32) -> Self {
+        self.legacy_version = version;
+        self
+    }
+
+    /// Register the upgrade from version `from` (stored as `Old`) to `from + 1` (`New`)
+    pub fn migrate<Old, New, M>(self, from: u32, migration: M) -> Self
+    where
+        Old: serde::de::DeserializeOwned + 'static,
+        New: 'static,
+        M: Fn(Old) -> New + Send + Sync + 'static,
+    {
+        self.try_migrate(from, move |old: Old| Ok(migration(old)))
+    }
+
+    /// Register a fallible upgrade from version `from` (stored as `Old`) to `from + 1` (`New`)
+    pub fn try_migrate<Old, New, M>(mut self, from: u32, migration: M) -> Self
+    where
+        Old: serde::de::DeserializeOwned + 'static,
+        New: 'static,
+        M: Fn(Old) -> io::Result<New> + Send + Sync + 'static,
+    {
+        let upgrade = move |value: AnyValue| -> io::Result<AnyValue> {
+            let old = value.downcast::<Old>().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("migration from schema version {from} registered for a different type"),
+                )
+            })?;
+            Ok(Box::new(migration(*old)?))
+        };
+        self.migrations.insert(
+            from,
+            Migration {
+                decode: decode_any::<F, Old>,
+                upgrade: Box::new(upgrade),
+            },
+        );
+        self
+    }
+
+    /// Deserialize `data`, upgrading it to `T::VERSION` if it is older
+    pub fn decode(&self, data: &[u8]) -> io::Result<T> {
+        let (version, payload) = split_versioned(data).unwrap_or((self.legacy_version, data));
+        if version == T::VERSION {
+            return self.format.decode(payload);
+        }
+        if version > T::VERSION {
+            return Err(version_mismatch(version, T::VERSION));
+        }
+
+        let first = self
+            .migrations
+            .get(&version)
+            .ok_or_else(|| version_mismatch(version, T::VERSION))?;
+        let mut value = (first.decode)(&self.format, payload)?;
+        for step in version..T::VERSION {
+            let migration = self.migrations.get(&step).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("no migration from schema version {step} to {}", step + 1),
+                )
+            })?;
+            value = (migration.upgrade)(value)?;
+        }
+        value.downcast::<T>().map(|value| *value).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "migration to schema version {} produces a different type",
+                    T::VERSION
+                ),
+            )
+        })
+    }
+
+    /// Unwrap an envelope with [`unwrap_auto`] and decode its payload
+    pub fn unwrap(&self, expected_kind: PayloadKind, data: &[u8]) -> io::Result<T> {
+        self.decode(&unwrap_auto(expected_kind, data)?)
+    }
+
+    /// Read a versioned file, upgrading it if needed
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> io::Result<T> {
+        self.decode(&std::fs::read(path)?)
+    }
+
+    /// Versions this registry can upgrade from
+    pub fn versions(&self) -> impl Iterator<Item = u32> + '_ {
+        self.migrations.keys().copied()
+    }
+}
+
+impl<T: Versioned, F: SerializationFormat + fmt::Debug> fmt::Debug for MigrationRegistry<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MigrationRegistry")
+            .field("format", &self.format)
+            .field("current_version", &T::VERSION)
+            .field("legacy_version", &self.legacy_version)
+            .field("migrations", &self.migrations.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Json;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct V0 {
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct V1 {
+        name: String,
+        dim: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V2 {
+        label: String,
+        dim: u32,
+        tags: Vec<String>,
+    }
+
+    impl Versioned for V1 {
+        const VERSION: u32 = 1;
+    }
+
+    impl Versioned for V2 {
+        const VERSION: u32 = 2;
+    }
+
+    fn registry() -> MigrationRegistry<V2> {
+        MigrationRegistry::new(Bincode)
+            .migrate(0, |v0: V0| V1 {
+                name: v0.name,
+                dim: 64,
+            })
+            .migrate(1, |v1: V1| V2 {
+                label: v1.name,
+                dim: v1.dim,
+                tags: Vec::new(),
+            })
+    }
+
+    #[test]
+    fn test_step_by_step_migration() {
+        let registry = registry();
+        let expected = V2 {
+            label: "e".into(),
+            dim: 64,
+            tags: Vec::new(),
+        };
+
+        // Unversioned legacy data is version 0
+        let legacy = crate::io::to_bincode(&V0 { name: "e".into() }).unwrap();
+        assert_eq!(registry.decode(&legacy).unwrap(), expected);
+
+        let v1 = to_versioned(
+            Bincode,
+            &V1 {
+                name: "e".into(),
+                dim: 64,
+            },
+        )
+        .unwrap();
+        assert_eq!(split_versioned(&v1).unwrap().0, 1);
+        assert_eq!(registry.decode(&v1).unwrap(), expected);
+
+        let current = to_versioned(Bincode, &expected).unwrap();
+        assert_eq!(registry.decode(&current).unwrap(), expected);
+        assert_eq!(
+            from_versioned::<_, V2>(Bincode, &current).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_version_errors_are_descriptive() {
+        let v1 = to_versioned(
+            Json,
+            &V1 {
+                name: "e".into(),
+                dim: 8,
+            },
+        )
+        .unwrap();
+        let err = from_versioned::<_, V2>(Json, &v1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string().contains("schema version 1, expected 2"),
+            "{err}"
+        );
+
+        let mut future = v1.clone();
+        future[4..8].copy_from_slice(&9u32.to_le_bytes());
+        let err = MigrationRegistry::<V2, _>::new(Json)
+            .decode(&future)
+            .unwrap_err();
+        assert!(err.to_string().contains("newer"), "{err}");
+
+        // Gap in the chain: only 1→2 registered, data at version 0
+        let registry = MigrationRegistry::<V2, _>::new(Json).migrate(1, |v1: V1| V2 {
+            label: v1.name,
+            dim: v1.dim,
+            tags: Vec::new(),
+        });
+        let legacy = crate::io::to_json(&V0 { name: "e".into() }).unwrap();
+        assert!(registry.decode(legacy.as_bytes()).is_err());
+        assert_eq!(registry.versions().collect::<Vec<_>>(), vec![1]);
+    }
+}
@@ -0,0 +1,139 @@
+//! Integration tests for the `edn-io` command-line tool
+
+#![cfg(feature = "cli")]
+
+use std::process::{Command, Output};
+
+use embeddenator_io::*;
+use serde_json::Value;
+use tempfile::tempdir;
+
+fn edn_io(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_edn-io"))
+        .args(args)
+        .output()
+        .expect("failed to run edn-io")
+}
+
+fn json_output(args: &[&str]) -> Value {
+    let output = edn_io(args);
+    serde_json::from_slice(&output.stdout).expect("edn-io did not emit JSON")
+}
+
+#[test]
+fn test_inspect_legacy_file() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("legacy.bin");
+    std::fs::write(&path, b"plain payload").unwrap();
+
+    let report = json_output(&["--json", "inspect", path.to_str().unwrap()]);
+    assert_eq!(report["format"], "legacy");
+    assert_eq!(report["file_len"], 13);
+    assert!(report["header"].is_null());
+}
+
+#[test]
+fn test_profile_json() {
+    let report = json_output(&["--json", "profile", "/boot/vmlinuz"]);
+    assert_eq!(report["profile"], "Kernel");
+    assert_eq!(report["codec"], "zstd");
+    assert_eq!(report["level"], 19);
+}
+
+#[test]
+fn test_verify_legacy_and_corrupt() {
+    let dir = tempdir().unwrap();
+    let legacy = dir.path().join("legacy.bin");
+    std::fs::write(&legacy, b"no header here").unwrap();
+
+    let output = edn_io(&["--json", "verify", legacy.to_str().unwrap()]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["status"], "legacy");
+
+    // Valid header for an uncompressed envelope, but the size field lies
+    let corrupt = dir.path().join("corrupt.bin");
+    let mut data = b"EDN1".to_vec();
+    data.extend_from_slice(&[PayloadKind::EngramBincode as u8, 0, 0, 0]);
+    data.extend_from_slice(&100u64.to_le_bytes());
+    data.extend_from_slice(b"short");
+    std::fs::write(&corrupt, data).unwrap();
+
+    let output = edn_io(&["--json", "verify", corrupt.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["status"], "corrupt");
+    assert_eq!(report["header"]["kind"], "engram");
+}
+
+#[cfg(feature = "compression-zstd")]
+#[test]
+fn test_wrap_inspect_unwrap_roundtrip() {
+    let dir = tempdir().unwrap();
+    let raw = dir.path().join("raw.bin");
+    let wrapped = dir.path().join("wrapped.bin");
+    let unwrapped = dir.path().join("unwrapped.bin");
+    let data = b"engram payload ".repeat(64);
+    std::fs::write(&raw, &data).unwrap();
+
+    let output = edn_io(&[
+        "wrap",
+        "--kind",
+        "sub-engram",
+        "--codec",
+        "zstd",
+        "--level",
+        "5",
+        raw.to_str().unwrap(),
+        wrapped.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let report = json_output(&["--json", "inspect", wrapped.to_str().unwrap()]);
+    assert_eq!(report["format"], "envelope");
+    assert_eq!(report["header"]["kind"], "sub-engram");
+    assert_eq!(report["header"]["codec"], "zstd");
+    assert_eq!(report["header"]["uncompressed_len"], data.len());
+
+    let output = edn_io(&[
+        "unwrap",
+        "--kind",
+        "sub-engram",
+        wrapped.to_str().unwrap(),
+        unwrapped.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&unwrapped).unwrap(), data);
+}
+
+#[cfg(feature = "compression-lz4")]
+#[test]
+fn test_compress_decompress_roundtrip() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("input.bin");
+    let compressed = dir.path().join("input.bin.lz4");
+    let restored = dir.path().join("restored.bin");
+    let data = b"streaming compression through the cli ".repeat(100);
+    std::fs::write(&input, &data).unwrap();
+
+    let report = json_output(&[
+        "--json",
+        "compress",
+        "--codec",
+        "lz4",
+        input.to_str().unwrap(),
+        compressed.to_str().unwrap(),
+    ]);
+    assert_eq!(report["input_len"], data.len());
+
+    let report = json_output(&[
+        "--json",
+        "decompress",
+        "--codec",
+        "lz4",
+        compressed.to_str().unwrap(),
+        restored.to_str().unwrap(),
+    ]);
+    assert_eq!(report["output_len"], data.len());
+    assert_eq!(std::fs::read(&restored).unwrap(), data);
+}
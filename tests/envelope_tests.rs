@@ -216,3 +216,204 @@ fn test_codec_comparison() {
     assert_eq!(zstd_unwrapped, data);
     assert_eq!(lz4_unwrapped, data);
 }
+
+#[test]
+fn test_header_parse() {
+    // Legacy data has no header
+    assert_eq!(EnvelopeHeader::parse(b"plain data").unwrap(), None);
+
+    let mut data = b"EDN1".to_vec();
+    data.extend_from_slice(&[PayloadKind::SubEngramBincode as u8, 0, 0, 0]);
+    data.extend_from_slice(&4u64.to_le_bytes());
+    data.extend_from_slice(b"abcd");
+
+    let header = EnvelopeHeader::parse(&data).unwrap().unwrap();
+    assert_eq!(header.kind, PayloadKind::SubEngramBincode);
+    assert_eq!(header.codec, CompressionCodec::None);
+    assert_eq!(header.uncompressed_len, 4);
+
+    // Unknown codec byte is rejected
+    data[5] = 0xFF;
+    assert!(EnvelopeHeader::parse(&data).is_err());
+}
+
+#[test]
+fn test_kind_and_codec_names() {
+    for kind in [
+        PayloadKind::EngramBincode,
+        PayloadKind::SubEngramBincode,
+        PayloadKind::Chunk,
+        PayloadKind::MerkleTree,
+        PayloadKind::MessagePack,
+        PayloadKind::Cbor,
+        PayloadKind::Postcard,
+    ] {
+        assert_eq!(PayloadKind::from_name(kind.name()), Some(kind));
+    }
+    for codec in [
+        CompressionCodec::None,
+        CompressionCodec::Zstd,
+        CompressionCodec::Lz4,
+    ] {
+        assert_eq!(CompressionCodec::from_name(codec.name()), Some(codec));
+    }
+    assert_eq!(
+        CompressionCodec::from_name("ZSTD"),
+        Some(CompressionCodec::Zstd)
+    );
+    assert_eq!(PayloadKind::from_name("bogus"), None);
+}
+
+#[test]
+fn test_wrap_always_writes_header() {
+    // Raw data that looks like an envelope must survive a round trip
+    let mut data = b"EDN1".to_vec();
+    data.extend_from_slice(&[PayloadKind::EngramBincode as u8, 0, 0, 0]);
+    data.extend_from_slice(&0u64.to_le_bytes());
+
+    let wrapped = wrap(PayloadKind::Chunk, BinaryWriteOptions::default(), &data).unwrap();
+    let header = EnvelopeHeader::parse(&wrapped).unwrap().unwrap();
+    assert_eq!(header.kind, PayloadKind::Chunk);
+    assert_eq!(header.codec, CompressionCodec::None);
+    assert_eq!(unwrap_auto(PayloadKind::Chunk, &wrapped).unwrap(), data);
+}
+
+#[test]
+fn test_rewrap_legacy_to_none_is_identity() {
+    let raw = b"legacy payload";
+    let out = rewrap(
+        raw,
+        BinaryWriteOptions::default(),
+        PayloadKind::EngramBincode,
+    )
+    .unwrap();
+    assert_eq!(out, raw);
+}
+
+#[cfg(all(feature = "compression-zstd", feature = "compression-lz4"))]
+#[test]
+fn test_rewrap_changes_codec_and_keeps_kind() {
+    let data = b"rewrap test data ".repeat(50);
+    let lz4 = BinaryWriteOptions {
+        codec: CompressionCodec::Lz4,
+        level: None,
+    };
+    let zstd = BinaryWriteOptions {
+        codec: CompressionCodec::Zstd,
+        level: Some(3),
+    };
+
+    let wrapped = wrap_or_legacy(PayloadKind::SubEngramBincode, lz4, &data).unwrap();
+    // legacy_kind is ignored for enveloped input
+    let rewrapped = rewrap(&wrapped, zstd, PayloadKind::EngramBincode).unwrap();
+
+    let header = EnvelopeHeader::parse(&rewrapped).unwrap().unwrap();
+    assert_eq!(header.kind, PayloadKind::SubEngramBincode);
+    assert_eq!(header.codec, CompressionCodec::Zstd);
+    assert_eq!(
+        unwrap_auto(PayloadKind::SubEngramBincode, &rewrapped).unwrap(),
+        data
+    );
+
+    // Legacy input is wrapped as the given kind
+    let from_legacy = rewrap(&data, lz4, PayloadKind::EngramBincode).unwrap();
+    let header = EnvelopeHeader::parse(&from_legacy).unwrap().unwrap();
+    assert_eq!(header.kind, PayloadKind::EngramBincode);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_envelopes_share_a_stream() {
+    use embeddenator_io::async_envelope::{read_envelope_prefixed, write_envelope_prefixed};
+    use embeddenator_io::async_serialize::{read_bincode_prefixed, write_bincode_prefixed};
+
+    let mut wire = Vec::new();
+    write_envelope_prefixed(
+        &mut wire,
+        PayloadKind::EngramBincode,
+        BinaryWriteOptions::default(),
+        b"first",
+    )
+    .await
+    .unwrap();
+    write_bincode_prefixed(&mut wire, &vec![1u32, 2, 3])
+        .await
+        .unwrap();
+
+    let mut reader = &wire[..];
+    let first = read_envelope_prefixed(&mut reader, PayloadKind::EngramBincode)
+        .await
+        .unwrap();
+    assert_eq!(first.as_deref(), Some(&b"first"[..]));
+    let second: Option<Vec<u32>> = read_bincode_prefixed(&mut reader).await.unwrap();
+    assert_eq!(second, Some(vec![1, 2, 3]));
+    assert!(
+        read_envelope_prefixed(&mut reader, PayloadKind::EngramBincode)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // A value cut short by the peer is an error, not end of stream
+    let mut truncated = &wire[..wire.len() - 2];
+    read_envelope_prefixed(&mut truncated, PayloadKind::EngramBincode)
+        .await
+        .unwrap();
+    let err = read_bincode_prefixed::<_, Vec<u32>>(&mut truncated)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_read_envelope_and_json() {
+    use embeddenator_io::async_envelope::{read_envelope, write_envelope};
+    use embeddenator_io::async_serialize::read_json;
+
+    let mut wire = Vec::new();
+    write_envelope(
+        &mut wire,
+        PayloadKind::SubEngramBincode,
+        BinaryWriteOptions::default(),
+        b"payload",
+    )
+    .await
+    .unwrap();
+    let raw = read_envelope(&mut &wire[..], PayloadKind::SubEngramBincode)
+        .await
+        .unwrap();
+    assert_eq!(raw, b"payload");
+
+    let value: Vec<String> = read_json(&mut &br#"["a","b"]"#[..]).await.unwrap();
+    assert_eq!(value, vec!["a", "b"]);
+}
+
+#[test]
+fn test_unwrap_borrowed() {
+    let raw = b"borrowed payload";
+    let legacy = unwrap_borrowed(PayloadKind::EngramBincode, raw).unwrap();
+    assert_eq!(legacy.as_ptr(), raw.as_ptr());
+
+    let opts = BinaryWriteOptions {
+        codec: CompressionCodec::None,
+        level: None,
+    };
+    let wrapped = wrap(PayloadKind::EngramBincode, opts, raw).unwrap();
+    let payload = unwrap_borrowed(PayloadKind::EngramBincode, &wrapped).unwrap();
+    assert_eq!(payload, raw);
+    assert_eq!(payload.as_ptr(), wrapped[EnvelopeHeader::LEN..].as_ptr());
+    assert!(unwrap_borrowed(PayloadKind::SubEngramBincode, &wrapped).is_err());
+}
+
+#[cfg(feature = "compression-zstd")]
+#[test]
+fn test_unwrap_borrowed_rejects_compressed() {
+    let opts = BinaryWriteOptions {
+        codec: CompressionCodec::Zstd,
+        level: None,
+    };
+    let wrapped = wrap(PayloadKind::EngramBincode, opts, b"payload").unwrap();
+    let err = unwrap_borrowed(PayloadKind::EngramBincode, &wrapped).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -323,3 +323,32 @@ fn test_buffer_sizes() {
         assert_eq!(reconstructed, data);
     }
 }
+
+#[test]
+fn test_file_writes_leave_no_temp_files() {
+    let dir = tempdir().unwrap();
+    let data = SampleData::sample();
+
+    write_bincode_file(dir.path().join("a.bin"), &data).unwrap();
+    write_json_file(dir.path().join("a.json"), &data).unwrap();
+    stream_write_file(dir.path().join("a.raw"), [b"abc", b"def"].into_iter()).unwrap();
+    write_bincode_file_with_options(
+        dir.path().join("b.bin"),
+        &data,
+        FileWriteOptions {
+            atomic: false,
+            durability: Durability::Data,
+        },
+    )
+    .unwrap();
+
+    let mut names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["a.bin", "a.json", "a.raw", "b.bin"]);
+
+    let loaded: SampleData = read_bincode_file(dir.path().join("b.bin")).unwrap();
+    assert_eq!(loaded, data);
+}